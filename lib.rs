@@ -2,32 +2,559 @@
 
 #[ink::contract]
 mod ssal_commods {
-    use ink::storage::Mapping;
+    use ink::prelude::{
+        string::String,
+        vec::Vec,
+    };
+    use ink::env::call::{
+        build_call,
+        ExecutionInput,
+        Selector,
+    };
+    use ink::storage::{
+        traits::StorageKey,
+        Lazy,
+        Mapping,
+    };
     use scale::{
         Decode,
         Encode,
+        EncodeLike,
     };
 
     pub type ContractId = u64;
+    pub type SeriesId = u32;
     pub type Grams = u64;
+    /// Hundredths of a percent, e.g. `1_400` is 14%.
+    pub type BasisPoints = u32;
+    /// A quality reading is measured on the same attributes as the spec it is graded against.
+    pub type QualityReading = QualitySpec;
+
+    /// Grams in a kilogram.
+    const GRAMS_PER_KILOGRAM: Grams = 1_000;
+    /// Grams in a metric tonne.
+    const GRAMS_PER_TONNE: Grams = 1_000_000;
+
+    /// Maximum number of entries a paginated query returns in one call.
+    const MAX_PAGE_SIZE: u64 = 100;
+
+    /// Maximum number of entries a batch message processes in one call.
+    const MAX_BATCH_SIZE: usize = 50;
+
+    /// Upper bound for any basis point value (100%).
+    const MAX_BASIS_POINTS: BasisPoints = 10_000;
+
+    /// Blocks a contract is kept in full after it settles or is cancelled, before
+    /// it can be archived (a day at 6 second blocks).
+    const ARCHIVE_RETENTION: BlockNumber = 14_400;
+
+    /// Blocks an inspector has after the finality block to attest delivery, before
+    /// the buyer can finalize on its own word (a day at 6 second blocks).
+    const ATTESTATION_WINDOW: BlockNumber = 14_400;
+
+    /// Upper bound for either protocol fee (10%).
+    const MAX_PROTOCOL_FEE: BasisPoints = 1_000;
+
+    /// Storage layout this code reads and writes. Storage written by code that
+    /// predates versioning is at version `0` until `migrate` completes.
+    const STORAGE_VERSION: u32 = 1;
+
+    /// Selector of the PSP22 `transfer` message of settlement tokens.
+    const PSP22_TRANSFER: [u8; 4] = [0xdb, 0x20, 0xf9, 0xf5];
+
+    /// Selector of the PSP22 `transfer_from` message of settlement tokens.
+    const PSP22_TRANSFER_FROM: [u8; 4] = [0x54, 0xb3, 0xc7, 0x6e];
 
     /// Used to query all contract specs at the same time.
     #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub struct CommodityContract {
+        series: Option<SeriesId>,
         seller: Option<AccountId>,
         buyer: Option<AccountId>,
         price: Option<Balance>,
         total: Option<Balance>,
         weight: Option<Grams>,
+        quantity: Option<Quantity>,
+        unit_price: Option<Balance>,
         finality_block:Option<BlockNumber>,
-        finalized: Option<bool>
+        finalized: Option<bool>,
+        quality_spec: Option<QualitySpec>,
+        quality_schedule: Option<QualitySchedule>,
+        quality_reading: Option<QualityReading>,
+        inspector: Option<AccountId>,
+        inspector_fee: Option<Balance>,
+        attestation: Option<Attestation>,
+        cancelled: Option<bool>,
+        deposit: Option<Balance>,
+        settlement_asset: Option<SettlementAsset>,
+    }
+
+    impl CommodityContract {
+        /// Returns the commitment `archive_contract` keeps for this record once
+        /// it is archived as contract `id`.
+        pub fn commitment(&self, id: ContractId) -> Hash {
+            let mut output = <ink::env::hash::Blake2x256 as ink::env::hash::HashOutput>::Type::default();
+            ink::env::hash_encoded::<ink::env::hash::Blake2x256, _>(&(id, self), &mut output);
+            Hash::from(output)
+        }
+    }
+
+    /// Parameters of a single lot listed through `create_contracts`.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ListingParams {
+        pub series: SeriesId,
+        pub price: Balance,
+        pub total: Balance,
+        pub weight: Grams,
+        pub finality_block: BlockNumber,
+    }
+
+    /// How a batch message handles entries that fail.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum BatchMode {
+        /// Every entry must succeed, otherwise the whole batch is rejected.
+        Atomic,
+        /// Entries are processed one by one and failures are reported per entry.
+        BestEffort,
+    }
+
+    /// Asset the price and total of a contract are paid in.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum SettlementAsset {
+        /// The token of this contract.
+        #[default]
+        Internal,
+        /// An external PSP22 token deployed at the given account.
+        Psp22(AccountId),
+        /// The native currency of the chain, paid with `buy_contract_native`.
+        Native,
+    }
+
+    /// Criteria open listings must meet to be returned by `search_listings`.
+    ///
+    /// Every bound is inclusive and unset bounds match any value.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ListingFilter {
+        pub min_price: Option<Balance>,
+        pub max_price: Option<Balance>,
+        pub min_total: Option<Balance>,
+        pub max_total: Option<Balance>,
+        pub min_weight: Option<Grams>,
+        pub max_weight: Option<Grams>,
+        pub min_finality_block: Option<BlockNumber>,
+        pub max_finality_block: Option<BlockNumber>,
+        pub seller: Option<AccountId>,
+    }
+
+    impl ListingFilter {
+        /// Returns whether `contract` meets every criteria of the filter.
+        pub fn matches(&self, contract: &CommodityContract) -> bool {
+            fn within<T: PartialOrd>(value: Option<T>, min: Option<T>, max: Option<T>) -> bool {
+                match value {
+                    Some(value) => {
+                        min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+                    }
+                    None => false,
+                }
+            }
+            within(contract.price, self.min_price, self.max_price)
+                && within(contract.total, self.min_total, self.max_total)
+                && within(contract.weight, self.min_weight, self.max_weight)
+                && within(contract.finality_block, self.min_finality_block, self.max_finality_block)
+                && self.seller.is_none_or(|seller| contract.seller == Some(seller))
+        }
+    }
+
+    /// Page of open listings returned by `search_listings`.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ListingPage {
+        /// Matching listings along with their ContractId.
+        pub listings: Vec<(ContractId, CommodityContract)>,
+        /// Cursor to resume the search from, or `None` once every listing was scanned.
+        pub next_cursor: Option<u64>,
+    }
+
+    /// Running totals of an account's commodity positions.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Exposure {
+        /// Contracts listed by the account that are still waiting for a buyer.
+        pub open_listings: u64,
+        /// Contracts bought by the account that have not settled yet.
+        pub open_positions: u64,
+        /// Escrow held by the contract account on behalf of the account as buyer.
+        pub locked: Balance,
+        /// Escrow the account expects to receive as seller once its contracts settle.
+        pub receivable: Balance,
+    }
+
+    /// Summary of an account's position returned by `portfolio_of`.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Portfolio {
+        /// Tokens the account can spend right away.
+        pub free_balance: Balance,
+        /// Commodity positions of the account.
+        pub exposure: Exposure,
+    }
+
+    /// Invariant of `SsalCommods` found broken by `audit`.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Violation {
+        /// The balances of every holder do not add up to the total supply.
+        SupplyMismatch {
+            total_supply: Balance,
+            sum_of_balances: Balance,
+        },
+        /// The contract account does not hold the total of every bought but unsettled
+        /// contract and the deposit of every open listing.
+        EscrowMismatch {
+            pooled: Balance,
+            outstanding: Balance,
+        },
+        /// A finalized contract still has funds in escrow.
+        SettledEscrow {
+            contract_id: ContractId,
+            escrow: Balance,
+        },
+        /// The native balance the contract account can spend without dropping below
+        /// the existential deposit does not cover the native escrow, unclaimed payouts
+        /// and the reserve backing deposited tokens.
+        NativeShortfall {
+            spendable: Balance,
+            outstanding: Balance,
+        },
+    }
+
+    /// Result of `audit`, listing every broken invariant.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct AuditReport {
+        pub violations: Vec<Violation>,
+    }
+
+    impl AuditReport {
+        /// Returns whether every invariant holds.
+        pub fn is_sound(&self) -> bool {
+            self.violations.is_empty()
+        }
+    }
+
+    /// Privilege an account can be granted.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Role {
+        /// Administers every role whose admin role was not changed with `set_role_admin`.
+        Admin,
+        /// Settles disputed or stuck contracts with `arbitrate`.
+        Arbiter,
+        /// May be named by sellers to attest delivery of their contracts.
+        Inspector,
+        /// Reports off-chain data such as quality readings.
+        Oracle,
+        /// May list contracts while the seller policy requires verified sellers.
+        VerifiedSeller,
+        /// Halts the marketplace in an incident.
+        Pauser,
+    }
+
+    /// Group of messages that can be paused together.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum PauseScope {
+        /// Creating contracts.
+        Listing,
+        /// Buying contracts.
+        Buying,
+        /// Finalizing contracts and settling them on attestation.
+        Settlement,
+        /// Token transfers requested by holders.
+        Transfers,
+    }
+
+    /// Stage of its lifecycle a contract is in.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum ContractStatus {
+        /// Listed and waiting for a buyer.
+        Open,
+        /// Bought, with the buyer's funds held in escrow.
+        Bought,
+        /// Settled, with the escrow paid out.
+        Finalized,
+        /// Withdrawn by the seller or expired before anyone bought it.
+        Cancelled,
+    }
+
+    /// Appends `id` to the contracts indexed under `key`.
+    fn index_push<K, E, C>(
+        entries: &mut Mapping<(K, u64), ContractId, E>,
+        counts: &mut Mapping<K, u64, C>,
+        key: K,
+        id: ContractId,
+    ) -> Result<(), Error>
+    where
+        K: EncodeLike + Copy,
+        E: StorageKey,
+        C: StorageKey,
+    {
+        let position = counts.get(key).unwrap_or_default();
+        let next_position = position.checked_add(1).ok_or(Error::Overflow)?;
+        entries.insert((key, position), &id);
+        counts.insert(key, &next_position);
+        Ok(())
+    }
+
+    /// Returns up to `limit` contracts indexed under `key`, starting at position `cursor`.
+    ///
+    /// At most `MAX_PAGE_SIZE` contracts are returned.
+    fn index_page<K, E, C>(
+        entries: &Mapping<(K, u64), ContractId, E>,
+        counts: &Mapping<K, u64, C>,
+        key: K,
+        cursor: u64,
+        limit: u64,
+    ) -> Vec<ContractId>
+    where
+        K: EncodeLike + Copy,
+        E: StorageKey,
+        C: StorageKey,
+    {
+        let count = counts.get(key).unwrap_or_default();
+        let end = count.min(cursor.saturating_add(limit.min(MAX_PAGE_SIZE)));
+        (cursor..end)
+            .filter_map(|position| entries.get((key, position)))
+            .collect()
+    }
+
+    /// A contract that passed the settlement checks, ready to be paid out.
+    struct Settlement {
+        seller: AccountId,
+        buyer: AccountId,
+        reading: Option<QualityReading>,
+        delivered_grams: Option<Grams>,
+    }
+
+    /// Unit of measure a quantity is expressed in.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Unit {
+        Grams,
+        Kilograms,
+        Tonnes,
+        /// Sacks holding the given number of grams each, e.g. `Sack(80_000)`.
+        Sack(Grams),
+    }
+
+    impl Unit {
+        /// Returns how many grams make up one of this unit.
+        pub fn grams(self) -> Grams {
+            match self {
+                Unit::Grams => 1,
+                Unit::Kilograms => GRAMS_PER_KILOGRAM,
+                Unit::Tonnes => GRAMS_PER_TONNE,
+                Unit::Sack(grams) => grams,
+            }
+        }
+    }
+
+    /// An amount of product in a given unit of measure.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Quantity {
+        pub amount: u64,
+        pub unit: Unit,
+    }
+
+    impl Quantity {
+        /// Returns the quantity in grams, or `None` if it does not fit in `Grams`.
+        pub fn to_grams(self) -> Option<Grams> {
+            self.amount.checked_mul(self.unit.grams())
+        }
+
+        /// Returns the quantity expressed in `unit`.
+        ///
+        /// Returns `None` if the conversion overflows, `unit` is an empty sack, or
+        /// the quantity is not a whole number of `unit`.
+        pub fn convert_to(self, unit: Unit) -> Option<Quantity> {
+            let grams = self.to_grams()?;
+            let per_unit = unit.grams();
+            if per_unit == 0 || grams % per_unit != 0 {
+                return None
+            }
+            Some(Quantity { amount: grams / per_unit, unit })
+        }
+    }
+
+    /// Standardized commodity every listing of a series trades,
+    /// e.g. "Japonica, grade 1, delivered to Busan port, per tonne".
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Series {
+        /// Commodity type, e.g. "Japonica".
+        pub commodity: String,
+        /// Grade of the commodity, where `1` is the best grade.
+        pub grade: u8,
+        /// Port or warehouse the commodity is delivered to.
+        pub delivery_location: String,
+        /// Unit the commodity is quoted in.
+        pub unit: Unit,
+        /// Whether new contracts can still be listed under this series.
+        pub active: bool,
+    }
+
+    /// Quality attributes a lot of rice is priced on.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct QualitySpec {
+        /// Grade of the lot, where `1` is the best grade.
+        pub grade: u8,
+        /// Moisture content in basis points.
+        pub moisture: BasisPoints,
+        /// Share of broken kernels in basis points.
+        pub broken_kernels: BasisPoints,
+        /// Variety code agreed upon by buyer and seller.
+        pub variety: u32,
+    }
+
+    /// Premium/discount schedule applied to `total` when a reading deviates from the spec.
+    ///
+    /// Every rate is expressed in basis points of `total`. Moisture and broken kernel
+    /// rates apply per percentage point above the spec, grade rates apply per grade
+    /// step, and the variety rate applies once on a mismatch.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct QualitySchedule {
+        pub moisture_discount: BasisPoints,
+        pub broken_kernel_discount: BasisPoints,
+        pub grade_premium: BasisPoints,
+        pub grade_discount: BasisPoints,
+        pub variety_discount: BasisPoints,
+    }
+
+    /// Requirements every new listing must meet.
+    #[derive(Encode, Decode, Debug, Default, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ListingRules {
+        /// Tokens the seller puts up per listing, returned once it is bought,
+        /// cancelled or expires.
+        pub deposit: Balance,
+        /// Lightest lot that can be listed.
+        pub min_weight: Grams,
+        /// Smallest total a listing can ask for.
+        pub min_total: Balance,
+        /// Furthest ahead of the current block a finality block can be, if limited.
+        pub max_finality_horizon: Option<BlockNumber>,
+    }
+
+    /// Cut of each sale the marketplace keeps, sent to its treasury.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct ProtocolFees {
+        /// Share of `price` withheld from the seller when a contract is bought.
+        pub purchase_fee: BasisPoints,
+        /// Share of the `total` owed to the seller withheld when a contract settles.
+        pub settlement_fee: BasisPoints,
+        /// Account credited with both fees.
+        pub treasury: AccountId,
+    }
+
+    /// Delivery certificate submitted by a contract's designated inspector.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Attestation {
+        /// Inspector who certified the delivery.
+        pub inspector: AccountId,
+        /// Weight, in grams, the inspector saw delivered.
+        pub delivered_grams: Grams,
+        /// Quality the inspector measured, if the contract is graded.
+        pub quality_reading: Option<QualityReading>,
+        /// Hash of the off-chain inspection report.
+        pub report_hash: Hash,
+        /// Block at which the attestation was submitted.
+        pub block: BlockNumber,
+    }
+
+    /// Returns `amount * part / whole` without overflowing, for any `part <= whole`.
+    fn proportion(amount: Balance, part: Balance, whole: Balance) -> Balance {
+        amount / whole * part + amount % whole * part / whole
+    }
+
+    impl QualitySchedule {
+        /// Returns the net discount, in basis points of `total`, for `reading` graded
+        /// against `spec`.
+        ///
+        /// Premiums can only offset discounts since the escrow never holds more than
+        /// `total`, so the result is clamped between `0` and `MAX_BASIS_POINTS`.
+        pub fn discount(&self, spec: &QualitySpec, reading: &QualityReading) -> BasisPoints {
+            let per_point = |measured: BasisPoints, agreed: BasisPoints, rate: BasisPoints| {
+                u64::from(measured.saturating_sub(agreed)) * u64::from(rate) / 100
+            };
+            let mut discount = per_point(reading.moisture, spec.moisture, self.moisture_discount)
+                + per_point(reading.broken_kernels, spec.broken_kernels, self.broken_kernel_discount)
+                + u64::from(reading.grade.saturating_sub(spec.grade)) * u64::from(self.grade_discount);
+            if reading.variety != spec.variety {
+                discount += u64::from(self.variety_discount);
+            }
+            let premium = u64::from(spec.grade.saturating_sub(reading.grade)) * u64::from(self.grade_premium);
+            discount
+                .saturating_sub(premium)
+                .min(u64::from(MAX_BASIS_POINTS)) as BasisPoints
+        }
     }
 
     #[ink(storage)]
     #[derive(Default)]
     pub struct SsalCommods {
         /// Commodity Contract Data
+        /// Registry series a given contract is listed under.
+        series: Mapping<ContractId, SeriesId>,
         /// Seller for a given contract.
         seller: Mapping<ContractId, AccountId>,
         /// Buyer for a given contract.
@@ -38,21 +565,136 @@ mod ssal_commods {
         total: Mapping<ContractId, Balance>,
         /// weight of product being sold as specified by the contract. 
         weight: Mapping<ContractId, Grams>, 
+        /// Quantity of product being sold, in the unit the seller listed it in.
+        quantity: Mapping<ContractId, Quantity>,
+        /// Price per unit of `quantity` for contracts whose total is derived from it.
+        unit_price: Mapping<ContractId, Balance>,
         /// Block number at which buyer's funds are locked if the seller doesn't.
         finality_block: Mapping<ContractId, BlockNumber>,
         /// Whether or not the contract has been finalized.
         finalized: Mapping<ContractId, bool>,
+        /// Quality the seller commits to deliver for a given contract.
+        quality_spec: Mapping<ContractId, QualitySpec>,
+        /// Premium/discount schedule applied at settlement for a given contract.
+        quality_schedule: Mapping<ContractId, QualitySchedule>,
+        /// Quality reading reported at settlement for a given contract.
+        quality_reading: Mapping<ContractId, QualityReading>,
+        /// Neutral third party who certifies delivery for a given contract.
+        inspector: Mapping<ContractId, AccountId>,
+        /// Fee paid to the inspector out of the escrow for a given contract.
+        inspector_fee: Mapping<ContractId, Balance>,
+        /// Delivery certificate submitted by the inspector for a given contract.
+        attestation: Mapping<ContractId, Attestation>,
+        /// Whether a given contract was withdrawn or expired before it was bought.
+        cancelled: Mapping<ContractId, bool>,
+        /// Asset a given contract settles in, if not the internal token.
+        settlement_asset: Mapping<ContractId, SettlementAsset>,
+        /// Block at which a given contract was settled or cancelled.
+        closed_at: Mapping<ContractId, BlockNumber>,
+        /// Hash of the final record of a given archived contract.
+        archive: Mapping<ContractId, Hash>,
         /// Running count for contracts which doubles as the ContractId for each consecutive contract.
         contract_count: ContractId, 
 
+        /// Index Data
+        /// Contracts listed by a seller, keyed by seller and position.
+        seller_contracts: Mapping<(AccountId, u64), ContractId>,
+        /// Number of contracts listed by a given seller.
+        seller_contract_count: Mapping<AccountId, u64>,
+        /// Contracts bought by a buyer, keyed by buyer and position.
+        buyer_contracts: Mapping<(AccountId, u64), ContractId>,
+        /// Number of contracts bought by a given buyer.
+        buyer_contract_count: Mapping<AccountId, u64>,
+        /// Contracts in a status, keyed by status and position.
+        status_contracts: Mapping<(ContractStatus, u64), ContractId>,
+        /// Number of contracts in a given status.
+        status_contract_count: Mapping<ContractStatus, u64>,
+        /// Position of a given contract among the contracts in its status.
+        status_position: Mapping<ContractId, u64>,
+        /// Running totals of the commodity positions of a given account.
+        exposure: Mapping<AccountId, Exposure>,
+
+        /// Escrow Data
+        /// Buyer's funds held in escrow for a given contract until it settles.
+        escrow: Mapping<ContractId, Balance>,
+        /// Sum of the escrow of every contract settling in the internal token, all of
+        /// it pooled on the contract account.
+        total_escrow: Lazy<Balance>,
+        /// Seller's listing deposit held for a given contract until it is bought,
+        /// cancelled or expires.
+        deposit: Mapping<ContractId, Balance>,
+        /// Sum of every listing deposit, pooled on the contract account with the escrow.
+        total_deposits: Lazy<Balance>,
+        /// Sum of the escrow of every contract settling in the native currency.
+        native_escrow: Lazy<Balance>,
+        /// Native currency owed to a given account whose payout could not be sent.
+        unclaimed_native: Mapping<AccountId, Balance>,
+        /// Sum of the native currency owed to every account.
+        total_unclaimed_native: Lazy<Balance>,
+        /// Native currency backing the tokens minted by `deposit`.
+        wrapped_reserve: Lazy<Balance>,
+
+        /// Registry Data
+        /// Account that deployed the contract and administers it.
+        owner: Lazy<Option<AccountId>>,
+        /// Account nominated by the owner to take over, until it accepts.
+        pending_owner: Lazy<Option<AccountId>>,
+        /// Registry entry for a given series.
+        series_entry: Mapping<SeriesId, Series>,
+        /// Running count for series which doubles as the SeriesId for each consecutive series.
+        series_count: Lazy<SeriesId>,
+        /// Contracts listed under a series, keyed by series and position within the series.
+        series_contracts: Mapping<(SeriesId, u64), ContractId>,
+        /// Number of contracts listed under a given series.
+        series_contract_count: Mapping<SeriesId, u64>,
+
+        /// Access Control Data
+        /// Accounts holding a given role.
+        role_members: Mapping<(Role, AccountId), ()>,
+        /// Role whose members grant and revoke a given role, `Admin` if unset.
+        role_admin: Mapping<Role, Role>,
+        /// Whether only `VerifiedSeller` members can list contracts.
+        require_verified_seller: Lazy<bool>,
+        /// Scopes currently paused.
+        paused: Mapping<PauseScope, ()>,
+
+        /// Upgrade Data
+        /// Layout version the storage has been migrated to. Kept out of the root
+        /// storage cell so code with more fields can still read it.
+        storage_version: Lazy<u32>,
+        /// Next contract `migrate` backfills.
+        migration_cursor: Lazy<ContractId>,
+        /// Whether the contract is still being seeded from a snapshot.
+        importing: Lazy<bool>,
+
+        /// Fee Data
+        /// Protocol fees charged on purchase and settlement, none if unset.
+        protocol_fees: Lazy<ProtocolFees>,
+        /// Requirements every new listing must meet, none if unset.
+        listing_rules: Lazy<ListingRules>,
+
         /// Token Data
         /// Total token supply.
         total_supply: Balance,
+        /// Total supply minting can never exceed, fixed at deployment, if any.
+        supply_cap: Lazy<Balance>,
         /// Mapping from owner to number of owned token.
         balances: Mapping<AccountId, Balance>,
         /// Mapping of the token amount which an account is allowed to withdraw
         /// from another account.
         allowances: Mapping<(AccountId, AccountId), Balance>,
+        /// Every account that ever held tokens, keyed by the order they first did.
+        holders: Mapping<u64, AccountId>,
+        /// Position of a given account in `holders`.
+        holder_position: Mapping<AccountId, u64>,
+        /// Number of accounts that ever held tokens.
+        holder_count: Lazy<u64>,
+        /// Name of the token, if any.
+        token_name: Lazy<String>,
+        /// Symbol of the token, if any.
+        token_symbol: Lazy<String>,
+        /// Number of decimals the token balances are denominated in.
+        token_decimals: Lazy<u8>,
     }
 
     /// Event emitted when a token transfer occurs.
@@ -76,14 +718,122 @@ mod ssal_commods {
         value: Balance,
     }
     
+    /// Event emitted when the owner nominates an account to take over the contract.
+    #[ink(event)]
+    pub struct OwnershipTransferStarted {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    /// Event emitted when ownership changes hands or is renounced.
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous_owner: Option<AccountId>,
+        #[ink(topic)]
+        new_owner: Option<AccountId>,
+    }
+
+    /// Event emitted when an account is granted a role.
+    #[ink(event)]
+    pub struct RoleGranted {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        sender: AccountId,
+    }
+
+    /// Event emitted when an account loses a role, by revocation or renunciation.
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        role: Role,
+        #[ink(topic)]
+        account: AccountId,
+        sender: AccountId,
+    }
+
+    /// Event emitted when the role administering another role changes.
+    #[ink(event)]
+    pub struct RoleAdminChanged {
+        #[ink(topic)]
+        role: Role,
+        previous_admin_role: Role,
+        new_admin_role: Role,
+    }
+
+    /// Event emitted when the owner changes who may list contracts.
+    #[ink(event)]
+    pub struct SellerPolicyChanged {
+        require_verified_seller: bool,
+    }
+
+    /// Event emitted when a scope is paused.
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        scope: PauseScope,
+        account: AccountId,
+    }
+
+    /// Event emitted when a scope is unpaused.
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        scope: PauseScope,
+        account: AccountId,
+    }
+
+    /// Event emitted when the code of the contract is replaced.
+    #[ink(event)]
+    pub struct Upgraded {
+        code_hash: Hash,
+    }
+
+    /// Event emitted when `migrate` brings the storage to a new layout version.
+    #[ink(event)]
+    pub struct Migrated {
+        version: u32,
+    }
+
+    /// Event emitted when a contract seeded from a snapshot stops accepting imports.
+    #[ink(event)]
+    pub struct ImportFinished {
+        contract_count: ContractId,
+        total_supply: Balance,
+    }
+
+    /// Event emitted when a series is added to the commodity registry.
+    #[ink(event)]
+    pub struct SeriesRegistered {
+        #[ink(topic)]
+        series_id: SeriesId,
+        commodity: String,
+        grade: u8,
+        delivery_location: String,
+        unit: Unit,
+    }
+
+    /// Event emitted when a series stops accepting new listings.
+    #[ink(event)]
+    pub struct SeriesRetired {
+        #[ink(topic)]
+        series_id: SeriesId,
+    }
+
     /// Event emitted when a contract is created.
     #[ink(event)]
     pub struct NewContract {
         contract_id: ContractId,
+        series: SeriesId,
         seller: AccountId,
         price: Balance,
         total: Balance,
         weight: Grams,
+        quantity: Quantity,
         finality_block: BlockNumber,
     }
 
@@ -94,6 +844,76 @@ mod ssal_commods {
         buyer: AccountId,
         price: Balance,
         total: Balance,
+        /// Amount of `price` paid to the treasury instead of the seller.
+        protocol_fee: Balance,
+    }
+
+    /// Event emitted when tokens sent to the contract account outside of escrow are recovered.
+    #[ink(event)]
+    pub struct StrayTokensSwept {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    /// Event emitted when the seller attaches a quality spec to a contract.
+    #[ink(event)]
+    pub struct QualitySpecified {
+        contract_id: ContractId,
+        spec: QualitySpec,
+        schedule: QualitySchedule,
+    }
+
+    /// Event emitted when the seller names an inspector for a contract.
+    #[ink(event)]
+    pub struct InspectorAssigned {
+        contract_id: ContractId,
+        inspector: AccountId,
+        fee: Balance,
+    }
+
+    /// Event emitted when the seller picks the asset a contract settles in.
+    #[ink(event)]
+    pub struct SettlementAssetSet {
+        contract_id: ContractId,
+        asset: SettlementAsset,
+    }
+
+    /// Event emitted when a native payout is held for its recipient to claim,
+    /// because the runtime rejected the transfer.
+    #[ink(event)]
+    pub struct PayoutDeferred {
+        #[ink(topic)]
+        account: AccountId,
+        value: Balance,
+    }
+
+    /// Event emitted when the native payouts held for an account are sent to `to`,
+    /// either claimed or combined with a later payout.
+    #[ink(event)]
+    pub struct PayoutClaimed {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+    }
+
+    /// Event emitted when an inspector certifies delivery of a contract.
+    #[ink(event)]
+    pub struct DeliveryAttested {
+        contract_id: ContractId,
+        inspector: AccountId,
+        delivered_grams: Grams,
+        report_hash: Hash,
+    }
+
+    /// Event emitted when an arbiter rules on the delivery of a contract.
+    #[ink(event)]
+    pub struct DisputeResolved {
+        contract_id: ContractId,
+        arbiter: AccountId,
+        delivered_grams: Grams,
     }
 
     /// Event emitted when a contract is finalized.
@@ -102,6 +922,51 @@ mod ssal_commods {
         contract_id: ContractId,
         buyer: AccountId,
         total: Balance,
+        /// Amount released to the seller after quality adjustments.
+        payout: Balance,
+        /// Amount refunded to the buyer after quality adjustments.
+        refund: Balance,
+        /// Amount paid to the inspector out of the seller's share.
+        inspector_fee: Balance,
+        /// Amount paid to the treasury out of the seller's share.
+        protocol_fee: Balance,
+    }
+
+    /// Event emitted when a listing is withdrawn by its seller or expires.
+    #[ink(event)]
+    pub struct ContractCancelled {
+        contract_id: ContractId,
+        seller: AccountId,
+        /// Whether the listing expired rather than being withdrawn.
+        expired: bool,
+        /// Listing deposit returned to the seller.
+        deposit: Balance,
+    }
+
+    /// Event emitted when a closed contract is removed from storage.
+    #[ink(event)]
+    pub struct ContractArchived {
+        #[ink(topic)]
+        contract_id: ContractId,
+        /// Final record of the contract, as returned by `get_contract`.
+        record: CommodityContract,
+        /// Hash of the record kept in storage.
+        commitment: Hash,
+    }
+
+    /// Event emitted when the owner changes the listing rules.
+    #[ink(event)]
+    pub struct ListingRulesChanged {
+        rules: ListingRules,
+    }
+
+    /// Event emitted when the owner changes the protocol fees.
+    #[ink(event)]
+    pub struct ProtocolFeesChanged {
+        purchase_fee: BasisPoints,
+        settlement_fee: BasisPoints,
+        #[ink(topic)]
+        treasury: AccountId,
     }
 
     #[derive(Encode, Decode, Debug, PartialEq, Eq, Copy, Clone)]
@@ -127,6 +992,145 @@ mod ssal_commods {
         CannotFinalizeBeforeFinalityBlock,
         /// Returned if caller tries to finalize a contract that has alread been finalzied 
         ContractAlreadyFinalized,
+        /// Returned if a caller other than the seller of a contract attempts to modify it.
+        OnlySellerCanModify,
+        /// Returned if a quality reading is reported for a contract without a quality spec.
+        QualitySpecNotFound,
+        /// Returned if a quality schedule rate exceeds `MAX_BASIS_POINTS`.
+        InvalidQualitySchedule,
+        /// Returned if an inspector fee exceeds the total of the contract.
+        InvalidInspectorFee,
+        /// Returned if the seller names an inspector without the Inspector role or
+        /// itself, or if the inspector of a contract attempts to buy it.
+        InvalidInspector,
+        /// Returned if a caller other than the designated inspector attempts to attest delivery.
+        OnlyInspectorCanAttest,
+        /// Returned if an inspector attests delivery of a contract a second time, or if
+        /// the buyer asks for a refund of an attested contract.
+        AlreadyAttested,
+        /// Returned if an inspected contract is settled before its inspector attested
+        /// delivery, unless the buyer finalizes after the attestation deadline.
+        AttestationNotFound,
+        /// Returned if the buyer reports a quality reading for an inspected contract.
+        OnlyInspectorCanGrade,
+        /// Returned if a caller other than a neutral oracle reports a quality reading for a
+        /// contract without an inspector.
+        OnlyOracleCanGrade,
+        /// Returned if a caller other than a neutral arbiter attempts to arbitrate
+        /// a contract.
+        OnlyArbiterCanResolve,
+        /// Returned if a caller other than the owner attempts an administrative action.
+        NotOwner,
+        /// Returned if a caller other than the pending owner attempts to accept ownership.
+        NotPendingOwner,
+        /// Returned if ownership is claimed on a contract that has an owner or whose
+        /// storage was written by code that had one.
+        OwnershipNotClaimable,
+        /// Returned if the owner renounces ownership before `migrate` has completed.
+        MigrationPending,
+        /// Returned if the caller lacks the role a message requires.
+        MissingRole,
+        /// Returned if `Admin` is granted, revoked, renounced or handed to another admin
+        /// role directly, since it only moves with ownership.
+        AdminRoleReserved,
+        /// Returned if an account without `VerifiedSeller` lists a contract while the
+        /// seller policy requires it.
+        SellerNotVerified,
+        /// Returned if a message is called while its scope is paused.
+        Paused,
+        /// Returned if an emergency refund is requested while settlement is not paused.
+        NotPaused,
+        /// Returned if an emergency refund is requested once the finality block of
+        /// the contract has passed, when the seller may already have delivered.
+        FinalityBlockPassed,
+        /// Returned if the runtime rejects the code hash given to `upgrade`.
+        UpgradeFailed,
+        /// Returned if an imported record is out of order, incomplete or already imported.
+        InvalidSnapshot,
+        /// Returned if records are imported into a contract not seeded from a
+        /// snapshot, or after `finish_import`.
+        ImportClosed,
+        /// Returned if a protocol fee exceeds `MAX_PROTOCOL_FEE`.
+        InvalidProtocolFee,
+        /// Returned if a listing is lighter than the listing rules allow.
+        WeightTooLow,
+        /// Returned if a listing asks for a smaller total than the listing rules allow.
+        TotalTooLow,
+        /// Returned if a listing's finality block is further ahead than the listing rules allow.
+        FinalityTooFar,
+        /// Returned if a contract was withdrawn or expired before it was bought.
+        ContractCancelled,
+        /// Returned if a listing is expired before its finality block has passed.
+        ListingNotExpired,
+        /// Returned if a contract is archived before it is finalized or cancelled.
+        ContractNotClosed,
+        /// Returned if a contract is archived before `ARCHIVE_RETENTION` blocks
+        /// have passed since it closed.
+        RetentionPeriodNotOver,
+        /// Returned if a contract is to settle in a token it cannot call, such as
+        /// this contract itself.
+        InvalidSettlementAsset,
+        /// Returned if a settlement token rejects a transfer or cannot be called.
+        TokenTransferFailed,
+        /// Returned if a contract is bought through the message of another
+        /// settlement asset.
+        SettlementAssetMismatch,
+        /// Returned if the native currency sent to buy a contract differs from its
+        /// price plus total.
+        InvalidPayment,
+        /// Returned if the contract account cannot send native currency it owes,
+        /// e.g. because it would drop below the existential deposit.
+        NativeTransferFailed,
+        /// Returned if more native currency is withdrawn than was deposited.
+        InsufficientReserve,
+        /// Returned if minting would take the total supply above the supply cap.
+        CapExceeded,
+        /// Returned if tokens held by the contract account, which pools escrows and
+        /// deposits, are burned.
+        PoolNotBurnable,
+        /// Returned if a listing references a series that is not in the registry.
+        SeriesNotFound,
+        /// Returned if a listing references a series that no longer accepts listings.
+        SeriesRetired,
+        /// Returned if a unit is a sack of zero grams.
+        InvalidUnit,
+        /// Returned if a per-unit listing is not quoted in the unit of its series.
+        UnitMismatch,
+        /// Returned if a balance, allowance, escrow, quantity or counter would overflow.
+        Overflow,
+        /// Returned if a batch message is given more than `MAX_BATCH_SIZE` entries.
+        BatchTooLarge,
+        /// Returned if the pooled balance of the contract account differs from the sum of escrows.
+        EscrowMismatch,
+    }
+
+    /// Errors returned by the PSP22 token messages, as defined by the standard.
+    #[derive(Encode, Decode, Debug, PartialEq, Eq, Clone)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PSP22Error {
+        /// Returned for errors the standard does not name, carrying the name of
+        /// the underlying `Error`.
+        Custom(String),
+        /// Returned if not enough balance to fulfill a request is available.
+        InsufficientBalance,
+        /// Returned if not enough allowance to fulfill a request is available.
+        InsufficientAllowance,
+        /// Returned if recipient's address is zero. Never returned by this contract.
+        ZeroRecipientAddress,
+        /// Returned if sender's address is zero. Never returned by this contract.
+        ZeroSenderAddress,
+        /// Returned if a safe transfer check fails. Never returned by this contract.
+        SafeTransferCheckFailed(String),
+    }
+
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+                error => PSP22Error::Custom(ink::prelude::format!("{:?}", error)),
+            }
+        }
     }
 
     impl SsalCommods {
@@ -134,18 +1138,43 @@ mod ssal_commods {
         #[ink(constructor)]
         pub fn new(total_supply: Balance) -> Self {
             // Initialize Commodity Contract Data   
+            let series = Mapping::default();
             let seller = Mapping::default();
             let buyer = Mapping::default();
             let price = Mapping::default();
             let total = Mapping::default();
             let weight = Mapping::default();
+            let quantity = Mapping::default();
+            let unit_price = Mapping::default();
             let finality_block = Mapping::default();
             let finalized = Mapping::default();
+            let quality_spec = Mapping::default();
+            let quality_schedule = Mapping::default();
+            let quality_reading = Mapping::default();
+            let inspector = Mapping::default();
+            let inspector_fee = Mapping::default();
+            let attestation = Mapping::default();
+            let cancelled = Mapping::default();
             let contract_count = 0;
 
+            // Initialize Index Data
+            let seller_contracts = Mapping::default();
+            let seller_contract_count = Mapping::default();
+            let buyer_contracts = Mapping::default();
+            let buyer_contract_count = Mapping::default();
+            let status_contracts = Mapping::default();
+            let status_contract_count = Mapping::default();
+            let status_position = Mapping::default();
+            let exposure = Mapping::default();
+
+            // Initialize Registry Data
+            let caller = Self::env().caller();
+            let series_entry = Mapping::default();
+            let series_contracts = Mapping::default();
+            let series_contract_count = Mapping::default();
+
             // Initialize Token Data 
             let mut balances = Mapping::default();
-            let caller = Self::env().caller();
             balances.insert(caller, &total_supply);
             Self::env().emit_event(Transfer {
                 from: None,
@@ -154,34 +1183,151 @@ mod ssal_commods {
             });
 
             // Initialize storage
-            Self {
+            let mut contract = Self {
+                series,
                 seller,
                 buyer,
                 price, 
                 total,
                 weight, 
+                quantity,
+                unit_price,
                 finality_block,
                 finalized,
+                quality_spec,
+                quality_schedule,
+                quality_reading,
+                inspector,
+                inspector_fee,
+                attestation,
+                cancelled,
+                settlement_asset: Mapping::default(),
+                closed_at: Mapping::default(),
+                archive: Mapping::default(),
                 contract_count, 
+                seller_contracts,
+                seller_contract_count,
+                buyer_contracts,
+                buyer_contract_count,
+                status_contracts,
+                status_contract_count,
+                status_position,
+                exposure,
+                escrow: Mapping::default(),
+                total_escrow: Lazy::default(),
+                deposit: Mapping::default(),
+                total_deposits: Lazy::default(),
+                native_escrow: Lazy::default(),
+                unclaimed_native: Mapping::default(),
+                total_unclaimed_native: Lazy::default(),
+                wrapped_reserve: Lazy::default(),
+                owner: Lazy::default(),
+                pending_owner: Lazy::default(),
+                series_entry,
+                series_count: Lazy::default(),
+                series_contracts,
+                series_contract_count,
+                role_members: Mapping::default(),
+                role_admin: Mapping::default(),
+                require_verified_seller: Lazy::default(),
+                paused: Mapping::default(),
+                storage_version: Lazy::default(),
+                migration_cursor: Lazy::default(),
+                importing: Lazy::default(),
+                protocol_fees: Lazy::default(),
+                listing_rules: Lazy::default(),
                 total_supply,
+                supply_cap: Lazy::default(),
                 balances,
                 allowances: Default::default(),
+                holders: Mapping::default(),
+                holder_position: Mapping::default(),
+                holder_count: Lazy::default(),
+                token_name: Lazy::default(),
+                token_symbol: Lazy::default(),
+                token_decimals: Lazy::default(),
+            };
+            // The deployer holds the initial supply
+            contract.holders.insert(0, &caller);
+            contract.holder_position.insert(caller, &0);
+            contract.holder_count.set(&1);
+            contract.owner.set(&Some(caller));
+            contract.role_members.insert((Role::Admin, caller), &());
+            contract.storage_version.set(&STORAGE_VERSION);
+            Self::env().emit_event(OwnershipTransferred {
+                previous_owner: None,
+                new_owner: Some(caller),
+            });
+            contract
+        }
+
+        /// Creates an empty ssal contract to be seeded with records exported from
+        /// another deployment.
+        ///
+        /// The caller becomes the owner and the only account that can import
+        /// records, until it calls `finish_import`.
+        #[ink(constructor)]
+        pub fn new_from_snapshot() -> Self {
+            let mut contract = Self::new(0);
+            contract.importing.set(&true);
+            contract
+        }
+
+        /// Creates a new ssal contract whose token carries PSP22 metadata.
+        #[ink(constructor)]
+        pub fn new_with_metadata(
+            total_supply: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+        ) -> Self {
+            let mut contract = Self::new(total_supply);
+            if let Some(name) = name {
+                contract.token_name.set(&name);
+            }
+            if let Some(symbol) = symbol {
+                contract.token_symbol.set(&symbol);
+            }
+            contract.token_decimals.set(&decimals);
+            contract
+        }
+
+        /// Creates a new ssal contract whose total supply can never exceed `cap`.
+        ///
+        /// The cap cannot be changed once the contract is deployed.
+        ///
+        /// # Errors
+        ///
+        /// Returns CapExceeded if `total_supply` exceeds `cap`.
+        #[ink(constructor)]
+        pub fn new_capped(total_supply: Balance, cap: Balance) -> Result<Self, Error> {
+            if total_supply > cap {
+                return Err(Error::CapExceeded)
             }
+            let mut contract = Self::new(total_supply);
+            contract.supply_cap.set(&cap);
+            Ok(contract)
         }
 
 
         // READING DATA
 
         /// Returns the total token supply.
-        #[ink(message)]
+        #[ink(message, selector = 0x162df8c2)]
         pub fn total_supply(&self) -> Balance {
             self.total_supply
         }
 
-        /// Returns the account balance for the specified `owner`.
-        ///
-        /// Returns `0` if the account is non-existent.
+        /// Returns the total supply minting can never exceed, if any.
         #[ink(message)]
+        pub fn get_supply_cap(&self) -> Option<Balance> {
+            self.supply_cap.get()
+        }
+
+        /// Returns the account balance for the specified `owner`.
+        ///
+        /// Returns `0` if the account is non-existent.
+        #[ink(message, selector = 0x6568382f)]
         pub fn balance_of(&self, owner: AccountId) -> Balance {
             self.balance_of_impl(&owner)
         }
@@ -199,10 +1345,28 @@ mod ssal_commods {
             self.balances.get(owner).unwrap_or_default()
         }
 
+        /// Returns the escrow held by the contract account on behalf of `owner` for
+        /// contracts it bought that have not settled yet.
+        ///
+        /// These funds are not part of `balance_of`.
+        #[ink(message)]
+        pub fn locked_balance_of(&self, owner: AccountId) -> Balance {
+            self.exposure.get(owner).unwrap_or_default().locked
+        }
+
+        /// Returns the free balance and commodity positions of `owner`.
+        #[ink(message)]
+        pub fn portfolio_of(&self, owner: AccountId) -> Portfolio {
+            Portfolio {
+                free_balance: self.balance_of_impl(&owner),
+                exposure: self.exposure.get(owner).unwrap_or_default(),
+            }
+        }
+
         /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
         ///
         /// Returns `0` if no allowance has been set.
-        #[ink(message)]
+        #[ink(message, selector = 0x4d47d921)]
         pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
             self.allowance_impl(&owner, &spender)
         }
@@ -220,6 +1384,297 @@ mod ssal_commods {
             self.allowances.get((owner, spender)).unwrap_or_default()
         }
 
+        /// Returns the token name, if any.
+        #[ink(message, selector = 0x3d261bd4)]
+        pub fn token_name(&self) -> Option<String> {
+            self.token_name.get()
+        }
+
+        /// Returns the token symbol, if any.
+        #[ink(message, selector = 0x34205be5)]
+        pub fn token_symbol(&self) -> Option<String> {
+            self.token_symbol.get()
+        }
+
+        /// Returns the number of decimals token balances are denominated in.
+        #[ink(message, selector = 0x7271b782)]
+        pub fn token_decimals(&self) -> u8 {
+            self.token_decimals.get().unwrap_or_default()
+        }
+
+        /// Returns the account administering the contract
+        #[ink(message)]
+        pub fn get_owner(&self) -> Option<AccountId> {
+            self.owner.get().flatten()
+        }
+
+        /// Returns the account nominated to take over the contract, if any
+        #[ink(message)]
+        pub fn get_pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner.get().flatten()
+        }
+
+        /// Returns whether `account` holds `role`
+        #[ink(message)]
+        pub fn has_role(&self, role: Role, account: AccountId) -> bool {
+            self.role_members.contains((role, account))
+        }
+
+        /// Returns the role whose members grant and revoke `role`
+        #[ink(message)]
+        pub fn get_role_admin(&self, role: Role) -> Role {
+            self.role_admin.get(role).unwrap_or(Role::Admin)
+        }
+
+        /// Returns whether only `VerifiedSeller` members can list contracts
+        #[ink(message)]
+        pub fn requires_verified_seller(&self) -> bool {
+            self.require_verified_seller.get().unwrap_or_default()
+        }
+
+        /// Returns the storage layout version, `0` until a pre-versioning deployment is migrated
+        #[ink(message)]
+        pub fn get_storage_version(&self) -> u32 {
+            self.storage_version.get().unwrap_or_default()
+        }
+
+        /// Returns whether the contract still accepts records from a snapshot
+        #[ink(message)]
+        pub fn is_importing(&self) -> bool {
+            self.importing.get().unwrap_or_default()
+        }
+
+        /// Returns the contracts among the `limit` ids starting at `cursor`, to be
+        /// imported into another deployment with `import_contracts`. Archived
+        /// contracts are skipped.
+        ///
+        /// At most `MAX_PAGE_SIZE` ids are scanned.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn export_contracts(
+            &self,
+            cursor: ContractId,
+            limit: u64,
+        ) -> Result<Vec<(ContractId, CommodityContract)>, Error> {
+            self.ensure_owner()?;
+            let end = cursor
+                .saturating_add(limit.min(MAX_PAGE_SIZE))
+                .min(self.contract_count);
+            Ok((cursor..end)
+                .filter_map(|id| self.get_contract(id).ok().map(|contract| (id, contract)))
+                .collect())
+        }
+
+        /// Returns the commitments of the archived contracts among the `limit` ids
+        /// starting at `cursor`, to be imported into another deployment with
+        /// `import_archives`.
+        ///
+        /// At most `MAX_PAGE_SIZE` ids are scanned.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn export_archives(
+            &self,
+            cursor: ContractId,
+            limit: u64,
+        ) -> Result<Vec<(ContractId, Hash)>, Error> {
+            self.ensure_owner()?;
+            let end = cursor
+                .saturating_add(limit.min(MAX_PAGE_SIZE))
+                .min(self.contract_count);
+            Ok((cursor..end)
+                .filter_map(|id| self.archive.get(id).map(|commitment| (id, commitment)))
+                .collect())
+        }
+
+        /// Returns up to `limit` token balances starting at holder position
+        /// `cursor`, to be imported into another deployment with `import_balances`.
+        ///
+        /// The contract account is skipped: the escrow and deposits it holds are
+        /// recreated when the contracts are imported, so stray tokens should be
+        /// swept before exporting. At most `MAX_PAGE_SIZE` balances are returned.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn export_balances(
+            &self,
+            cursor: u64,
+            limit: u64,
+        ) -> Result<Vec<(AccountId, Balance)>, Error> {
+            self.ensure_owner()?;
+            let pool = self.env().account_id();
+            let end = cursor
+                .saturating_add(limit.min(MAX_PAGE_SIZE))
+                .min(self.holder_count());
+            Ok((cursor..end)
+                .filter_map(|position| self.holders.get(position))
+                .filter(|holder| *holder != pool)
+                .map(|holder| (holder, self.balance_of_impl(&holder)))
+                .collect())
+        }
+
+        /// Returns the protocol fees in force, if any
+        #[ink(message)]
+        pub fn get_protocol_fees(&self) -> Option<ProtocolFees> {
+            self.protocol_fees.get()
+        }
+
+        /// Returns whether the messages in `scope` are paused
+        #[ink(message)]
+        pub fn is_paused(&self, scope: PauseScope) -> bool {
+            self.paused.contains(scope)
+        }
+
+        /// Returns the registry entry at the given SeriesId
+        #[ink(message)]
+        pub fn get_series(&self, series: SeriesId) -> Option<Series> {
+            self.series_entry.get(series)
+        }
+
+        /// Returns the number of series in the registry.
+        #[ink(message)]
+        pub fn get_series_count(&self) -> SeriesId {
+            self.series_count.get().unwrap_or_default()
+        }
+
+        /// Returns the series at the given ContractId
+        #[ink(message)]
+        pub fn get_contract_series(&self, id: ContractId) -> Option<SeriesId> {
+            self.series.get(id)
+        }
+
+        /// Returns up to `limit` contracts listed under `series`, starting at
+        /// position `cursor` within the series.
+        ///
+        /// At most `MAX_PAGE_SIZE` contracts are returned. Pass the number of
+        /// returned ids plus `cursor` as the next cursor.
+        #[ink(message)]
+        pub fn contracts_by_series(
+            &self,
+            series: SeriesId,
+            cursor: u64,
+            limit: u64,
+        ) -> Vec<ContractId> {
+            index_page(&self.series_contracts, &self.series_contract_count, series, cursor, limit)
+        }
+
+        /// Returns up to `limit` contracts listed by `seller`, starting at
+        /// position `cursor`, in listing order.
+        ///
+        /// At most `MAX_PAGE_SIZE` contracts are returned.
+        #[ink(message)]
+        pub fn contracts_by_seller(
+            &self,
+            seller: AccountId,
+            cursor: u64,
+            limit: u64,
+        ) -> Vec<ContractId> {
+            index_page(&self.seller_contracts, &self.seller_contract_count, seller, cursor, limit)
+        }
+
+        /// Returns up to `limit` contracts bought by `buyer`, starting at
+        /// position `cursor`, in purchase order.
+        ///
+        /// At most `MAX_PAGE_SIZE` contracts are returned.
+        #[ink(message)]
+        pub fn contracts_by_buyer(
+            &self,
+            buyer: AccountId,
+            cursor: u64,
+            limit: u64,
+        ) -> Vec<ContractId> {
+            index_page(&self.buyer_contracts, &self.buyer_contract_count, buyer, cursor, limit)
+        }
+
+        /// Returns up to `limit` contracts in `status`, starting at position `cursor`.
+        ///
+        /// Contracts leaving a status are swapped out with the last contract of that
+        /// status, so the order is not stable across purchases and settlements.
+        /// At most `MAX_PAGE_SIZE` contracts are returned.
+        #[ink(message)]
+        pub fn contracts_by_status(
+            &self,
+            status: ContractStatus,
+            cursor: u64,
+            limit: u64,
+        ) -> Vec<ContractId> {
+            index_page(&self.status_contracts, &self.status_contract_count, status, cursor, limit)
+        }
+
+        /// Returns up to `limit` contracts still waiting for a buyer, starting at
+        /// position `cursor`.
+        #[ink(message)]
+        pub fn open_listings(&self, cursor: u64, limit: u64) -> Vec<ContractId> {
+            self.contracts_by_status(ContractStatus::Open, cursor, limit)
+        }
+
+        /// Searches open listings for the ones matching `filter`.
+        ///
+        /// Scans at most `MAX_PAGE_SIZE` listings starting at position `cursor` and
+        /// returns up to `limit` matches. When `filter.seller` is set the seller's
+        /// listings are scanned, otherwise every open listing is. Keep calling with
+        /// `next_cursor` until it is `None` to scan every listing.
+        #[ink(message)]
+        pub fn search_listings(
+            &self,
+            filter: ListingFilter,
+            cursor: u64,
+            limit: u64,
+        ) -> ListingPage {
+            let count = match filter.seller {
+                Some(seller) => self.seller_contract_count.get(seller),
+                None => self.status_contract_count.get(ContractStatus::Open),
+            }
+            .unwrap_or_default();
+            let end = count.min(cursor.saturating_add(MAX_PAGE_SIZE));
+
+            let mut listings = Vec::new();
+            let mut position = cursor;
+            while position < end && (listings.len() as u64) < limit {
+                let id = match filter.seller {
+                    Some(seller) => self.seller_contracts.get((seller, position)),
+                    None => self.status_contracts.get((ContractStatus::Open, position)),
+                };
+                position += 1;
+                let Some(id) = id else { continue };
+                if self.get_status(id) != Some(ContractStatus::Open) {
+                    continue
+                }
+                if let Ok(contract) = self.get_contract(id) {
+                    if filter.matches(&contract) {
+                        listings.push((id, contract));
+                    }
+                }
+            }
+
+            ListingPage {
+                listings,
+                next_cursor: (position < count).then_some(position),
+            }
+        }
+
+        /// Returns the status at the given ContractId
+        #[ink(message)]
+        pub fn get_status(&self, id: ContractId) -> Option<ContractStatus> {
+            self.seller.get(id)?;
+            if self.cancelled.get(id) == Some(true) {
+                Some(ContractStatus::Cancelled)
+            } else if self.finalized.get(id) == Some(true) {
+                Some(ContractStatus::Finalized)
+            } else if self.buyer.get(id).is_some() {
+                Some(ContractStatus::Bought)
+            } else {
+                Some(ContractStatus::Open)
+            }
+        }
+
         /// Returns seller's AccountId at the given ContractId
         #[ink(message)]
         pub fn get_seller(&self, id: ContractId) -> Option<AccountId> {
@@ -250,6 +1705,18 @@ mod ssal_commods {
             self.weight.get(id)
         }
 
+        /// Returns quantity, in the unit it was listed in, at the given ContractId
+        #[ink(message)]
+        pub fn get_quantity(&self, id: ContractId) -> Option<Quantity> {
+            self.quantity.get(id)
+        }
+
+        /// Returns price per unit at the given ContractId, for contracts listed per unit
+        #[ink(message)]
+        pub fn get_unit_price(&self, id: ContractId) -> Option<Balance> {
+            self.unit_price.get(id)
+        }
+
         /// Returns finality block at the given ContractId
         #[ink(message)]
         pub fn get_finality_block(&self, id: ContractId) -> Option<BlockNumber> {
@@ -262,6 +1729,51 @@ mod ssal_commods {
             self.finalized.get(id)
         }
 
+        /// Returns the quality spec at the given ContractId
+        #[ink(message)]
+        pub fn get_quality_spec(&self, id: ContractId) -> Option<QualitySpec> {
+            self.quality_spec.get(id)
+        }
+
+        /// Returns the premium/discount schedule at the given ContractId
+        #[ink(message)]
+        pub fn get_quality_schedule(&self, id: ContractId) -> Option<QualitySchedule> {
+            self.quality_schedule.get(id)
+        }
+
+        /// Returns the quality reading reported at settlement for the given ContractId
+        #[ink(message)]
+        pub fn get_quality_reading(&self, id: ContractId) -> Option<QualityReading> {
+            self.quality_reading.get(id)
+        }
+
+        /// Returns the inspector at the given ContractId
+        #[ink(message)]
+        pub fn get_inspector(&self, id: ContractId) -> Option<AccountId> {
+            self.inspector.get(id)
+        }
+
+        /// Returns the inspector fee at the given ContractId
+        #[ink(message)]
+        pub fn get_inspector_fee(&self, id: ContractId) -> Option<Balance> {
+            self.inspector_fee.get(id)
+        }
+
+        /// Returns the delivery attestation at the given ContractId
+        #[ink(message)]
+        pub fn get_attestation(&self, id: ContractId) -> Option<Attestation> {
+            self.attestation.get(id)
+        }
+
+        /// Returns the last block the inspector of the given ContractId can attest
+        /// delivery before the buyer can finalize without it
+        #[ink(message)]
+        pub fn get_attestation_deadline(&self, id: ContractId) -> Option<BlockNumber> {
+            self.inspector.get(id)?;
+            let finality_block = self.finality_block.get(id)?;
+            Some(finality_block.saturating_add(ATTESTATION_WINDOW))
+        }
+
         /// Returns the contract count at the given ContractId.
         #[ink(message)]
         pub fn get_contract_count(&self) -> ContractId {
@@ -274,25 +1786,222 @@ mod ssal_commods {
             match self.seller.get(id) {
                 Some(_) => {
                     Ok(CommodityContract{ 
+                        series: self.series.get(id),
                         seller: self.seller.get(id),
                         buyer: self.buyer.get(id),
                         price: self.price.get(id),
                         total: self.total.get(id),
                         weight: self.weight.get(id),
+                        quantity: self.quantity.get(id),
+                        unit_price: self.unit_price.get(id),
                         finality_block: self.finality_block.get(id),
                         finalized: self.finalized.get(id),
+                        quality_spec: self.quality_spec.get(id),
+                        quality_schedule: self.quality_schedule.get(id),
+                        quality_reading: self.quality_reading.get(id),
+                        inspector: self.inspector.get(id),
+                        inspector_fee: self.inspector_fee.get(id),
+                        attestation: self.attestation.get(id),
+                        cancelled: self.cancelled.get(id),
+                        deposit: self.deposit.get(id),
+                        settlement_asset: self.settlement_asset.get(id),
                     })
                 }
                 None => Err(Error::ContractNotFound)
             }
         }
 
+        /// Returns the hash of the final record of an archived contract
+        ///
+        /// The record itself is carried by the `ContractArchived` event, and
+        /// `CommodityContract::commitment` recomputes the hash from it.
+        #[ink(message)]
+        pub fn get_archive_commitment(&self, id: ContractId) -> Option<Hash> {
+            self.archive.get(id)
+        }
+
+        /// Returns the block at which the given contract was settled or cancelled
+        #[ink(message)]
+        pub fn get_closed_at(&self, id: ContractId) -> Option<BlockNumber> {
+            self.closed_at.get(id)
+        }
+
+        /// Returns the asset the given ContractId settles in
+        #[ink(message)]
+        pub fn get_settlement_asset(&self, id: ContractId) -> Option<SettlementAsset> {
+            self.seller.get(id)?;
+            Some(self.settlement_asset_of(id))
+        }
+
+        /// Returns the asset a contract settles in, the internal token unless the
+        /// seller picked another.
+        fn settlement_asset_of(&self, id: ContractId) -> SettlementAsset {
+            self.settlement_asset.get(id).unwrap_or_default()
+        }
+
+        /// Returns how much of `value` paid for a contract is pooled on the internal
+        /// token balance of the contract account.
+        fn pooled(&self, id: ContractId, value: Balance) -> Balance {
+            match self.settlement_asset_of(id) {
+                SettlementAsset::Internal => value,
+                SettlementAsset::Psp22(_) | SettlementAsset::Native => 0,
+            }
+        }
+
+        /// Returns the escrow held for the given ContractId, in its settlement asset
+        ///
+        /// Returns `0` once the contract has settled or if it was never bought.
+        #[ink(message)]
+        pub fn escrow_of(&self, id: ContractId) -> Balance {
+            self.escrow.get(id).unwrap_or_default()
+        }
+
+        /// Returns the sum of the escrow of every contract settling in the internal token.
+        #[ink(message)]
+        pub fn total_escrow(&self) -> Balance {
+            self.total_escrow.get().unwrap_or_default()
+        }
+
+        /// Returns the listing deposit held for the given ContractId
+        ///
+        /// Returns `0` once the contract is bought, cancelled or expired.
+        #[ink(message)]
+        pub fn deposit_of(&self, id: ContractId) -> Balance {
+            self.deposit.get(id).unwrap_or_default()
+        }
+
+        /// Returns the sum of every listing deposit.
+        #[ink(message)]
+        pub fn total_deposits(&self) -> Balance {
+            self.total_deposits.get().unwrap_or_default()
+        }
+
+        /// Returns the requirements every new listing must meet
+        #[ink(message)]
+        pub fn get_listing_rules(&self) -> ListingRules {
+            self.listing_rules.get().unwrap_or_default()
+        }
+
+        /// Returns the tokens held by the contract account that are neither
+        /// escrowed nor deposited for any contract, e.g. sent to it with `transfer`.
+        #[ink(message)]
+        pub fn stray_tokens(&self) -> Balance {
+            self.balance_of_impl(&self.env().account_id())
+                .saturating_sub(self.total_escrow())
+                .saturating_sub(self.total_deposits())
+        }
+
+        /// Checks that the tokens pooled on the contract account match the sum
+        /// of escrows and listing deposits.
+        ///
+        /// # Errors
+        ///
+        /// Returns EscrowMismatch if the pool holds less than the sum of escrows
+        /// and deposits, or more because of stray tokens that have not been swept yet.
+        #[ink(message)]
+        pub fn check_escrow(&self) -> Result<(), Error> {
+            let held = self.total_escrow().checked_add(self.total_deposits());
+            if Some(self.balance_of_impl(&self.env().account_id())) != held {
+                return Err(Error::EscrowMismatch)
+            }
+            Ok(())
+        }
+
+        /// Recomputes the invariants of the contract from scratch and reports every
+        /// one that is broken:
+        ///
+        /// - the balances of every holder add up to `total_supply`,
+        /// - the contract account holds the `total` of every bought but unsettled
+        ///   contract settling in the internal token and the deposit of every open
+        ///   listing,
+        /// - no finalized contract has funds left in escrow,
+        /// - the native balance of the contract account, less the existential
+        ///   deposit, covers the native escrow, unclaimed payouts and the reserve
+        ///   backing deposited tokens.
+        ///
+        /// This walks every holder and every contract, so it is meant to be
+        /// dry-run rather than submitted as a transaction.
+        #[ink(message)]
+        pub fn audit(&self) -> AuditReport {
+            let mut violations = Vec::new();
+
+            let sum_of_balances = (0..self.holder_count())
+                .filter_map(|position| self.holders.get(position))
+                .fold(0, |sum: Balance, holder| sum.saturating_add(self.balance_of_impl(&holder)));
+            if sum_of_balances != self.total_supply {
+                violations.push(Violation::SupplyMismatch {
+                    total_supply: self.total_supply,
+                    sum_of_balances,
+                });
+            }
+
+            let mut outstanding: Balance = 0;
+            for id in 0..self.contract_count {
+                if self.buyer.get(id).is_none() {
+                    if self.cancelled.get(id) != Some(true) {
+                        outstanding = outstanding.saturating_add(self.deposit_of(id));
+                    }
+                    continue
+                }
+                if self.finalized.get(id) == Some(true) {
+                    let escrow = self.escrow_of(id);
+                    if escrow > 0 {
+                        violations.push(Violation::SettledEscrow { contract_id: id, escrow });
+                    }
+                } else if self.settlement_asset_of(id) == SettlementAsset::Internal {
+                    let total = self.total.get(id).unwrap_or_default();
+                    outstanding = outstanding.saturating_add(total);
+                }
+            }
+            let pooled = self.balance_of_impl(&self.env().account_id());
+            if pooled != outstanding {
+                violations.push(Violation::EscrowMismatch { pooled, outstanding });
+            }
+
+            let spendable = self.env().balance().saturating_sub(self.env().minimum_balance());
+            let outstanding = self
+                .native_escrow()
+                .saturating_add(self.total_unclaimed_native())
+                .saturating_add(self.wrapped_reserve());
+            if spendable < outstanding {
+                violations.push(Violation::NativeShortfall { spendable, outstanding });
+            }
+
+            AuditReport { violations }
+        }
+
         /// Returns the balance of the contract account 
         #[ink(message)]
         pub fn get_balance(&self) -> Balance {
             self.env().balance()
         }
 
+        /// Returns the sum of the escrow of every contract settling in the native currency.
+        #[ink(message)]
+        pub fn native_escrow(&self) -> Balance {
+            self.native_escrow.get().unwrap_or_default()
+        }
+
+        /// Returns the native currency held for `account` to claim with `claim_native`
+        /// or `claim_native_to`.
+        #[ink(message)]
+        pub fn unclaimed_native_of(&self, account: AccountId) -> Balance {
+            self.unclaimed_native.get(account).unwrap_or_default()
+        }
+
+        /// Returns the sum of the native currency held for every account to claim.
+        #[ink(message)]
+        pub fn total_unclaimed_native(&self) -> Balance {
+            self.total_unclaimed_native.get().unwrap_or_default()
+        }
+
+        /// Returns the native currency held on the contract account against tokens
+        /// minted by `deposit`.
+        #[ink(message)]
+        pub fn wrapped_reserve(&self) -> Balance {
+            self.wrapped_reserve.get().unwrap_or_default()
+        }
+
         /// Returns the current block number
         #[ink(message)]
         pub fn get_block(&self) -> BlockNumber {
@@ -304,81 +2013,293 @@ mod ssal_commods {
 
         /// Transfers `value` amount of tokens from the caller's account to account `to`.
         ///
+        /// `data` is passed along by PSP22 callers and ignored. Transferring zero
+        /// tokens or to the caller itself succeeds without a `Transfer` event.
+        ///
         /// On success a `Transfer` event is emitted.
         ///
         /// # Errors
         ///
+        /// Returns `Custom("Paused")` error if transfers are paused.
+        ///
         /// Returns `InsufficientBalance` error if there are not enough tokens on
         /// the caller's account balance.
-        #[ink(message)]
-        pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), Error> {
+        #[ink(message, selector = 0xdb20f9f5)]
+        pub fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_unpaused(PauseScope::Transfers)?;
             let from = self.env().caller();
-            self.transfer_from_to(&from, &to, value)
+            if from == to || value == 0 {
+                return Ok(())
+            }
+            Ok(self.transfer_from_to(&from, &to, value)?)
         }
 
         /// Allows `spender` to withdraw from the caller's account multiple times, up to
         /// the `value` amount.
         ///
         /// If this function is called again it overwrites the current allowance with
-        /// `value`.
+        /// `value`. Approving the caller itself succeeds without an event.
         ///
         /// An `Approval` event is emitted.
-        #[ink(message)]
-        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), Error> {
+        #[ink(message, selector = 0xb20f1bbd)]
+        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            if owner == spender {
+                return Ok(())
+            }
+            self.set_allowance(owner, spender, value);
+            Ok(())
+        }
+
+        /// Raises the allowance of `spender` over the caller's account by `delta_value`.
+        ///
+        /// An `Approval` event is emitted with the new allowance.
+        ///
+        /// # Errors
+        ///
+        /// Returns `Custom("Overflow")` error if the allowance would overflow.
+        #[ink(message, selector = 0x96d6b57a)]
+        pub fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            if owner == spender || delta_value == 0 {
+                return Ok(())
+            }
+            let allowance = self
+                .allowance_impl(&owner, &spender)
+                .checked_add(delta_value)
+                .ok_or(Error::Overflow)?;
+            self.set_allowance(owner, spender, allowance);
+            Ok(())
+        }
+
+        /// Lowers the allowance of `spender` over the caller's account by `delta_value`.
+        ///
+        /// An `Approval` event is emitted with the new allowance.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientAllowance` error if `delta_value` exceeds the
+        /// current allowance.
+        #[ink(message, selector = 0xfecb57d5)]
+        pub fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> Result<(), PSP22Error> {
             let owner = self.env().caller();
+            if owner == spender || delta_value == 0 {
+                return Ok(())
+            }
+            let allowance = self
+                .allowance_impl(&owner, &spender)
+                .checked_sub(delta_value)
+                .ok_or(PSP22Error::InsufficientAllowance)?;
+            self.set_allowance(owner, spender, allowance);
+            Ok(())
+        }
+
+        /// Sets the allowance of `spender` over `owner`'s account and emits an
+        /// `Approval` event.
+        fn set_allowance(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
             self.allowances.insert((&owner, &spender), &value);
             self.env().emit_event(Approval {
                 owner,
                 spender,
                 value,
             });
-            Ok(())
         }
 
         /// Transfers `value` tokens on the behalf of `from` to the account `to`.
         ///
         /// This can be used to allow a contract to transfer tokens on ones behalf and/or
-        /// to charge fees in sub-currencies, for example.
+        /// to charge fees in sub-currencies, for example. `data` is ignored.
+        /// Transferring zero tokens or from an account to itself succeeds without
+        /// an event.
         ///
-        /// On success a `Transfer` event is emitted.
+        /// On success a `Transfer` event and an `Approval` event with the remaining
+        /// allowance are emitted.
         ///
         /// # Errors
         ///
+        /// Returns `Custom("Paused")` error if transfers are paused.
+        ///
         /// Returns `InsufficientAllowance` error if there are not enough tokens allowed
         /// for the caller to withdraw from `from`.
         ///
         /// Returns `InsufficientBalance` error if there are not enough tokens on
         /// the account balance of `from`.
-        #[ink(message)]
+        #[ink(message, selector = 0x54b3c76e)]
         pub fn transfer_from(
             &mut self,
             from: AccountId,
             to: AccountId,
             value: Balance,
-        ) -> Result<(), Error> {
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            self.ensure_unpaused(PauseScope::Transfers)?;
+            if from == to || value == 0 {
+                return Ok(())
+            }
             let caller = self.env().caller();
             let allowance = self.allowance_impl(&from, &caller);
             if allowance < value {
-                return Err(Error::InsufficientAllowance)
+                return Err(PSP22Error::InsufficientAllowance)
             }
             self.transfer_from_to(&from, &to, value)?;
-            self.allowances
-                .insert((&from, &caller), &(allowance - value));
+            self.set_allowance(from, caller, allowance - value);
             Ok(())
         }
 
-        /// Transfers `value` amount of tokens from the caller's account to account `to`.
+        /// Mints tokens to the caller 1:1 against the native currency sent with the
+        /// call, which is held on the contract account until they are withdrawn.
         ///
-        /// On success a `Transfer` event is emitted.
+        /// On success a `Transfer` event from `None` is emitted.
         ///
         /// # Errors
         ///
-        /// Returns `InsufficientBalance` error if there are not enough tokens on
-        /// the caller's account balance.
-        fn transfer_from_to(
-            &mut self,
-            from: &AccountId,
-            to: &AccountId,
+        /// Returns Paused if transfers are paused.
+        ///
+        /// Returns CapExceeded if the total supply would exceed the supply cap.
+        ///
+        /// Returns Overflow if the total supply would overflow.
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) -> Result<(), Error> {
+            self.ensure_unpaused(PauseScope::Transfers)?;
+            let value = self.env().transferred_value();
+            if value == 0 {
+                return Ok(())
+            }
+            let reserve = self.wrapped_reserve().checked_add(value).ok_or(Error::Overflow)?;
+            self.mint_impl(&self.env().caller(), value)?;
+            self.wrapped_reserve.set(&reserve);
+            Ok(())
+        }
+
+        /// Burns `value` tokens of the caller and sends it the same amount of the
+        /// native currency.
+        ///
+        /// Withdrawals stay open while transfers are paused, so holders can always
+        /// exit to the native currency.
+        ///
+        /// On success a `Transfer` event to `None` is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns InsufficientBalance if the caller holds fewer than `value` tokens.
+        ///
+        /// Returns InsufficientReserve if `value` exceeds the native currency
+        /// deposited and not yet withdrawn.
+        ///
+        /// Returns NativeTransferFailed if the contract account cannot send `value`
+        /// without dropping below the existential deposit, or the runtime rejects
+        /// the transfer.
+        #[ink(message)]
+        pub fn withdraw(&mut self, value: Balance) -> Result<(), Error> {
+            if value == 0 {
+                return Ok(())
+            }
+            let caller = self.env().caller();
+            if self.balance_of_impl(&caller) < value {
+                return Err(Error::InsufficientBalance)
+            }
+            let reserve = self.wrapped_reserve().checked_sub(value).ok_or(Error::InsufficientReserve)?;
+            self.burn_impl(&caller, value)?;
+            self.wrapped_reserve.set(&reserve);
+            let spendable = self.env().balance().saturating_sub(self.env().minimum_balance());
+            if value > spendable || self.env().transfer(caller, value).is_err() {
+                return Err(Error::NativeTransferFailed)
+            }
+            Ok(())
+        }
+
+        /// Creates `value` tokens on the account of `to`.
+        ///
+        /// Only the owner can call this function.
+        ///
+        /// On success a `Transfer` event from `None` is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns CapExceeded if the total supply would exceed the supply cap.
+        ///
+        /// Returns Overflow if the total supply would overflow.
+        #[ink(message)]
+        pub fn mint(&mut self, to: AccountId, value: Balance) -> Result<(), Error> {
+            self.ensure_owner()?;
+            self.mint_impl(&to, value)
+        }
+
+        /// Destroys `value` tokens of the caller.
+        ///
+        /// On success a `Transfer` event to `None` is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns Paused if transfers are paused.
+        ///
+        /// Returns InsufficientBalance if the caller holds fewer than `value` tokens.
+        #[ink(message)]
+        pub fn burn(&mut self, value: Balance) -> Result<(), Error> {
+            self.ensure_unpaused(PauseScope::Transfers)?;
+            self.burn_impl(&self.env().caller(), value)
+        }
+
+        /// Destroys `value` tokens of `from`, spending the allowance it gave the
+        /// caller.
+        ///
+        /// On success a `Transfer` event to `None` is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns Paused if transfers are paused.
+        ///
+        /// Returns PoolNotBurnable if `from` is the contract account.
+        ///
+        /// Returns InsufficientAllowance if the caller is allowed to spend fewer
+        /// than `value` tokens of `from`.
+        ///
+        /// Returns InsufficientBalance if `from` holds fewer than `value` tokens.
+        #[ink(message)]
+        pub fn burn_from(&mut self, from: AccountId, value: Balance) -> Result<(), Error> {
+            self.ensure_unpaused(PauseScope::Transfers)?;
+            if from == self.env().account_id() {
+                return Err(Error::PoolNotBurnable)
+            }
+            let caller = self.env().caller();
+            let allowance = self.allowance_impl(&from, &caller);
+            if allowance < value {
+                return Err(Error::InsufficientAllowance)
+            }
+            self.burn_impl(&from, value)?;
+            self.set_allowance(from, caller, allowance - value);
+            Ok(())
+        }
+
+        /// Transfers `value` amount of tokens from the caller's account to account `to`.
+        ///
+        /// On success a `Transfer` event is emitted.
+        ///
+        /// # Errors
+        ///
+        /// Returns `InsufficientBalance` error if there are not enough tokens on
+        /// the caller's account balance.
+        ///
+        /// Returns `Overflow` error if the balance of `to` would overflow.
+        fn transfer_from_to(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
             value: Balance,
         ) -> Result<(), Error> {
             let from_balance = self.balance_of_impl(from);
@@ -386,9 +2307,13 @@ mod ssal_commods {
                 return Err(Error::InsufficientBalance)
             }
 
-            self.balances.insert(from, &(from_balance - value));
             let to_balance = self.balance_of_impl(to);
-            self.balances.insert(to, &(to_balance + value));
+            if from != to {
+                let credited = to_balance.checked_add(value).ok_or(Error::Overflow)?;
+                self.track_holder(to)?;
+                self.balances.insert(from, &(from_balance - value));
+                self.balances.insert(to, &credited);
+            }
             self.env().emit_event(Transfer {
                 from: Some(*from),
                 to: Some(*to),
@@ -397,349 +2322,5042 @@ mod ssal_commods {
             Ok(())
         }
 
-        /// Creates a new Ssal Contract
-        /// 
-        /// Adds inputted data to the relevant mappings, increments the contract 
-        /// count, and adds the caller as the seller for the contract.
-        /// 
+        /// Returns the number of accounts tracked in `holders`.
+        fn holder_count(&self) -> u64 {
+            self.holder_count.get().unwrap_or_default()
+        }
+
+        /// Adds `account` to the holders `audit` sums balances over, if it is not
+        /// tracked yet.
+        ///
         /// # Errors
-        /// 
-        /// Returns InvalidBlockNumber if the inputted finality_block is less than 
-        /// the current block number.
+        ///
+        /// Returns Overflow if the holder count would overflow.
+        fn track_holder(&mut self, account: &AccountId) -> Result<(), Error> {
+            if self.holder_position.contains(account) {
+                return Ok(())
+            }
+            let position = self.holder_count();
+            let holder_count = position.checked_add(1).ok_or(Error::Overflow)?;
+            self.holders.insert(position, account);
+            self.holder_position.insert(account, &position);
+            self.holder_count.set(&holder_count);
+            Ok(())
+        }
+
+        /// Adds a standardized commodity to the registry.
+        ///
+        /// Only the owner can call this function. The new series accepts listings
+        /// right away.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns InvalidUnit if `unit` is a sack of zero grams.
+        ///
+        /// Returns Overflow if no SeriesId is left to assign.
         #[ink(message)]
-        pub fn create_contract(
+        pub fn register_series(
             &mut self,
-            _price: Balance,
-            _total: Balance,
-            _weight: Grams,
-            _finality_block: BlockNumber
-        ) -> Result<(), Error> {
-            // Check that finality block is valid
-            if _finality_block < self.env().block_number(){
-                return Err(Error::InvalidBlockNumber);
+            commodity: String,
+            grade: u8,
+            delivery_location: String,
+            unit: Unit,
+        ) -> Result<SeriesId, Error> {
+            self.ensure_owner()?;
+            if unit.grams() == 0 {
+                return Err(Error::InvalidUnit)
             }
 
-            let caller = self.env().caller();
-            // Set contract data into relevant mappings
-            self.seller.insert(self.contract_count, &caller);
-            self.price.insert(self.contract_count, &_price);
-            self.total.insert(self.contract_count, &_total);
-            self.weight.insert(self.contract_count, &_weight);
-            self.finality_block.insert(self.contract_count, &_finality_block);
-            self.finalized.insert(self.contract_count, &false);
-
-            self.contract_count += 1;
+            let series_id = self.get_series_count();
+            let next_series = series_id.checked_add(1).ok_or(Error::Overflow)?;
+            let entry = Series {
+                commodity: commodity.clone(),
+                grade,
+                delivery_location: delivery_location.clone(),
+                unit,
+                active: true,
+            };
+            self.series_entry.insert(series_id, &entry);
+            self.series_count.set(&next_series);
 
-            Self::env().emit_event(NewContract {
-                contract_id: self.contract_count - 1,
-                seller: caller,
-                price: _price,
-                total: _total,
-                weight: _weight,
-                finality_block: _finality_block,
+            Self::env().emit_event(SeriesRegistered {
+                series_id,
+                commodity,
+                grade,
+                delivery_location,
+                unit,
             });
 
-            Ok(())
+            Ok(series_id)
         }
 
-        /// Buy a contract given a ContractId
-        /// 
-        /// Transfers funds (the amount designated by price) from buyer's account to 
-        /// seller's account.
-        /// 
-        /// Transfers funds (the amount designated by total) to the smart contract account 
-        /// to lockup the buyer's assets.
-        /// 
-        /// Adds buyer's AccountId to the buyer mapping if all the transfers goes through.
-        /// 
+        /// Stops a series from accepting new listings.
+        ///
+        /// Contracts already listed under the series are unaffected.
+        ///
         /// # Errors
-        /// 
-        /// Returns ContractNotFound if the seller cannot be found for the given contract.
-        /// 
-        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
-        /// 
-        /// Returns Insufficient Balance if the buyer does not have enough funds to
-        /// cover both the contract price and total price of the product.
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns SeriesNotFound if the series is not in the registry.
         #[ink(message)]
-        pub fn buy_contract(&mut self, id: ContractId) -> Result<(), Error> {
-            // Check wether contract exists
-            let seller = match self.seller.get(id) {
+        pub fn retire_series(&mut self, series: SeriesId) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            let mut entry = match self.series_entry.get(series) {
                 Some(p) => p,
-                None => return Err(Error::ContractNotFound)
+                None => return Err(Error::SeriesNotFound)
             };
-            // Check whether there is already a buyer.
-            match self.buyer.get(id) {
-                Some(_) => return Err(Error::ContractAlreadyBought),
-                None => ()
-            }
+            entry.active = false;
+            self.series_entry.insert(series, &entry);
 
-            // Fetch transactional variables
-            let caller = self.env().caller();
-            let price = self.price.get(id).unwrap();
-            let total = self.total.get(id).unwrap();
-            // Check caller has enough money
-            if self.balance_of(caller) < price + total {
-                return Err(Error::InsufficientBalance)
+            Self::env().emit_event(SeriesRetired { series_id: series });
+
+            Ok(())
+        }
+
+        /// Sends the tokens held by the contract account outside of escrow to `to`.
+        ///
+        /// Returns the amount swept. Escrowed funds are never touched.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn sweep_stray_tokens(&mut self, to: AccountId) -> Result<Balance, Error> {
+            self.ensure_owner()?;
+
+            let amount = self.stray_tokens();
+            if amount > 0 {
+                self.transfer_from_to(&self.env().account_id(), &to, amount)?;
             }
 
-            // Run transfers
-            self.transfer_from_to(&caller, &seller, price)?;
-            self.transfer_from_to(&caller, &self.env().account_id(), total)?;
-            // Add buyer to the relevant mapping
-            self.buyer.insert(id, &caller);
+            Self::env().emit_event(StrayTokensSwept { to, amount });
 
-            Self::env().emit_event(ContractBought {
-                contract_id: id,
-                buyer: caller,
-                price: price,
-                total: total,
+            Ok(amount)
+        }
+
+        /// Nominates `new_owner` to take over the contract.
+        ///
+        /// Ownership only moves once `new_owner` calls `accept_ownership`. Until then
+        /// the caller stays the owner, and can nominate someone else to replace the
+        /// pending nomination.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.pending_owner.set(&Some(new_owner));
+
+            Self::env().emit_event(OwnershipTransferStarted {
+                previous_owner: self.env().caller(),
+                new_owner,
             });
 
             Ok(())
         }
 
-        /// Finalize the contract: transfer total from the contract account to the seller.
-        /// 
-        /// The buyer calls this function when they have received their product.
-        /// 
-        /// Only the buyer can call this function.
-        /// 
-        /// This function can only be called at or after the finality block.
-        /// 
+        /// Makes the caller the owner, completing a transfer started with
+        /// `transfer_ownership`.
+        ///
+        /// `Admin` moves with ownership: it is revoked from the previous owner and
+        /// granted to the caller.
+        ///
         /// # Errors
-        /// 
-        /// Returns ContractNotFound if there is no seller for the given contract.
-        /// 
-        /// Returns CannotFinalizeBeforeFinalityBlock if the caller attempts to 
-        /// finalize the contract before the finality block.
-        /// 
-        /// Returns OnlyBuyerCanFinalize if a caller other than the buyer for the 
-        /// given contract attempts to finalize.
-        /// 
-        /// In theory, would return InsufficientBalance if the contract account 
-        /// does not have enough funds to pay the seller, but this should never
-        /// occur.
+        ///
+        /// Returns NotPendingOwner if the caller was not nominated by the owner.
         #[ink(message)]
-        pub fn finalize(&mut self, id: ContractId) -> Result<(), Error> {
-            // Check that contract exists.
-            let seller = match self.seller.get(id) {
-                Some(p) => p,
-                None => return Err(Error::ContractNotFound)
-            };
-            // Check that it has not already been finalized
-            if self.is_finalized(id).unwrap() == true {
-                return Err(Error::ContractAlreadyFinalized);
+        pub fn accept_ownership(&mut self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.get_pending_owner() != Some(caller) {
+                return Err(Error::NotPendingOwner)
             }
-            // Check that current block >= to the finality block of the contract
-            if self.finality_block.get(id).unwrap() >= self.env().block_number() {
-                return Err(Error::CannotFinalizeBeforeFinalityBlock)
+
+            let previous_owner = self.get_owner();
+            if let Some(previous_owner) = previous_owner {
+                self.remove_role(Role::Admin, previous_owner);
             }
-            // Check that contract has been bought.
-            let buyer = match self.buyer.get(id){
-                Some(p) => p,
-                None => { return Err(Error::ContractNotPurchased) }
-            };
-            // Check that buyer is caller
-            if self.env().caller() != buyer {
-                return Err(Error::OnlyBuyerCanFinalize)
+            self.add_role(Role::Admin, caller);
+            self.owner.set(&Some(caller));
+            self.pending_owner.set(&None);
+
+            Self::env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: Some(caller),
+            });
+
+            Ok(())
+        }
+
+        /// Gives up ownership for good, leaving every administrative message
+        /// uncallable. The caller loses `Admin` and any pending nomination is
+        /// dropped.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns MigrationPending if storage has not been migrated to
+        /// `STORAGE_VERSION` yet, since only the owner can complete it.
+        #[ink(message)]
+        pub fn renounce_ownership(&mut self) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if self.get_storage_version() < STORAGE_VERSION {
+                return Err(Error::MigrationPending)
             }
 
-            // Transfer total from contract account to seller 
-            let total = self.total.get(id).unwrap();
-            self.transfer_from_to(&self.env().account_id(), &seller, total)?;
+            let previous_owner = self.get_owner();
+            self.remove_role(Role::Admin, self.env().caller());
+            self.owner.set(&None);
+            self.pending_owner.set(&None);
 
-            self.finalized.insert(id, &true);
+            Self::env().emit_event(OwnershipTransferred {
+                previous_owner,
+                new_owner: None,
+            });
 
-            Self::env().emit_event(ContractFinalized {
-                contract_id: id,
-                buyer: buyer,
-                total: total,
+            Ok(())
+        }
+
+        /// Makes the caller the owner of a deployment upgraded from the original
+        /// code, which had no owner, so that it can be migrated and administered.
+        ///
+        /// Ownership can only be claimed once, before the storage is migrated: the
+        /// claimant becomes the owner and holds `Admin`, and cannot renounce until
+        /// `migrate` completes. Anyone can claim, so the claim should follow the
+        /// upgrade right away.
+        ///
+        /// # Errors
+        ///
+        /// Returns OwnershipNotClaimable if the contract has an owner or its storage
+        /// is past version `0`.
+        #[ink(message)]
+        pub fn claim_ownership(&mut self) -> Result<(), Error> {
+            if self.get_owner().is_some() || self.get_storage_version() > 0 {
+                return Err(Error::OwnershipNotClaimable)
+            }
+
+            let caller = self.env().caller();
+            self.add_role(Role::Admin, caller);
+            self.owner.set(&Some(caller));
+            self.pending_owner.set(&None);
+
+            Self::env().emit_event(OwnershipTransferred {
+                previous_owner: None,
+                new_owner: Some(caller),
             });
 
             Ok(())
         }
-    }
 
-    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
-    /// module and test functions are marked with a `#[test]` attribute.
-    /// The below code is technically just normal Rust code.
-    #[cfg(test)]
-    mod tests {
-        /// Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        /// Grants `role` to `account`. Granting a role the account already holds
+        /// does nothing.
+        ///
+        /// # Errors
+        ///
+        /// Returns MissingRole if the caller does not hold the admin role of `role`.
+        ///
+        /// Returns AdminRoleReserved if `role` is `Admin`, which only the owner holds.
+        #[ink(message)]
+        pub fn grant_role(&mut self, role: Role, account: AccountId) -> Result<(), Error> {
+            self.ensure_role(self.get_role_admin(role))?;
+            Self::ensure_delegable(role)?;
 
-        // COMMOD TESTS
+            self.add_role(role, account);
 
-        /// We test if the default constructor does its job.
-        #[ink::test]
-        fn default_works() {
-            let ssal = SsalCommods::new(100_000);
-            assert_eq!(ssal.get_contract_count(), 0);
-            assert_eq!(ssal.total_supply(), 100_000);
+            Ok(())
+        }
+
+        /// Revokes `role` from `account`. Revoking a role the account does not
+        /// hold does nothing.
+        ///
+        /// # Errors
+        ///
+        /// Returns MissingRole if the caller does not hold the admin role of `role`.
+        ///
+        /// Returns AdminRoleReserved if `role` is `Admin`, which only the owner holds.
+        #[ink(message)]
+        pub fn revoke_role(&mut self, role: Role, account: AccountId) -> Result<(), Error> {
+            self.ensure_role(self.get_role_admin(role))?;
+            Self::ensure_delegable(role)?;
+
+            self.remove_role(role, account);
+
+            Ok(())
+        }
+
+        /// Gives up `role` held by the caller. Renouncing a role the caller does
+        /// not hold does nothing.
+        ///
+        /// # Errors
+        ///
+        /// Returns AdminRoleReserved if `role` is `Admin`, which the owner gives up
+        /// with `renounce_ownership`.
+        #[ink(message)]
+        pub fn renounce_role(&mut self, role: Role) -> Result<(), Error> {
+            Self::ensure_delegable(role)?;
+
+            self.remove_role(role, self.env().caller());
+
+            Ok(())
+        }
+
+        /// Makes the members of `admin_role` the ones granting and revoking `role`.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns AdminRoleReserved if `role` is `Admin`, which only moves with
+        /// ownership.
+        #[ink(message)]
+        pub fn set_role_admin(&mut self, role: Role, admin_role: Role) -> Result<(), Error> {
+            self.ensure_owner()?;
+            Self::ensure_delegable(role)?;
+
+            let previous_admin_role = self.get_role_admin(role);
+            self.role_admin.insert(role, &admin_role);
+
+            Self::env().emit_event(RoleAdminChanged {
+                role,
+                previous_admin_role,
+                new_admin_role: admin_role,
+            });
+
+            Ok(())
+        }
+
+        /// Sets whether only `VerifiedSeller` members can list contracts.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn set_seller_policy(&mut self, require_verified_seller: bool) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.require_verified_seller.set(&require_verified_seller);
+
+            Self::env().emit_event(SellerPolicyChanged { require_verified_seller });
+
+            Ok(())
+        }
+
+        /// Pauses the messages in `scope` until `unpause` is called. Pausing a scope
+        /// that is already paused does nothing.
+        ///
+        /// Escrow held for bought contracts can still be returned to buyers with
+        /// `refund_escrow` while settlement is paused.
+        ///
+        /// # Errors
+        ///
+        /// Returns MissingRole if the caller is neither the owner nor a `Pauser`.
+        #[ink(message)]
+        pub fn pause(&mut self, scope: PauseScope) -> Result<(), Error> {
+            self.ensure_pauser()?;
+
+            if !self.is_paused(scope) {
+                self.paused.insert(scope, &());
+                Self::env().emit_event(Paused {
+                    scope,
+                    account: self.env().caller(),
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Resumes the messages in `scope`. Unpausing a scope that is not paused
+        /// does nothing.
+        ///
+        /// # Errors
+        ///
+        /// Returns MissingRole if the caller is neither the owner nor a `Pauser`.
+        #[ink(message)]
+        pub fn unpause(&mut self, scope: PauseScope) -> Result<(), Error> {
+            self.ensure_pauser()?;
+
+            if self.is_paused(scope) {
+                self.paused.remove(scope);
+                Self::env().emit_event(Unpaused {
+                    scope,
+                    account: self.env().caller(),
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Guards `pause` and `unpause`.
+        ///
+        /// # Errors
+        ///
+        /// Returns MissingRole if the caller is neither the owner nor a `Pauser`.
+        fn ensure_pauser(&self) -> Result<(), Error> {
+            let caller = self.env().caller();
+            if self.ensure_owner().is_err() && !self.has_role(Role::Pauser, caller) {
+                return Err(Error::MissingRole)
+            }
+            Ok(())
+        }
+
+        /// Guards every message that can be paused.
+        ///
+        /// # Errors
+        ///
+        /// Returns Paused if `scope` is paused.
+        fn ensure_unpaused(&self, scope: PauseScope) -> Result<(), Error> {
+            if self.is_paused(scope) {
+                return Err(Error::Paused)
+            }
+            Ok(())
+        }
+
+        /// Replaces the code of the contract with the code uploaded under `code_hash`,
+        /// keeping its storage and balance.
+        ///
+        /// Listings and balances carry over as long as the new code reads the same
+        /// root storage cell, which holds only `contract_count` and `total_supply`
+        /// as in the original code; every later scalar lives in a `Lazy` cell of its
+        /// own. If its `STORAGE_VERSION` is higher, the owner calls `migrate`
+        /// afterwards until it reports completion. A deployment of the original
+        /// code, which has no `upgrade` message, is upgraded by the chain's
+        /// privileged `set_code` instead and gets its owner with `claim_ownership`.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns UpgradeFailed if no code was uploaded under `code_hash`.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)?;
+
+            Self::env().emit_event(Upgraded { code_hash });
+
+            Ok(())
+        }
+
+        /// Brings storage written by older code up to `STORAGE_VERSION`.
+        ///
+        /// Contracts listed before the indexes, exposure and escrow ledger existed
+        /// only have their per-field entries. Each call backfills the next
+        /// `MAX_BATCH_SIZE` of them and returns whether the migration is complete.
+        /// Contracts that are already indexed are skipped, and once complete the
+        /// call does nothing, so it is safe to repeat.
+        ///
+        /// Legacy contracts have no series and are quoted in grams. Their sellers,
+        /// buyers and the contract account are added to the holders `audit` sums
+        /// over; accounts that only ever held tokens are added with
+        /// `backfill_holders`.
+        ///
+        /// The original code had no owner, so a deployment upgraded from it first
+        /// gets one with `claim_ownership`.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns Overflow if a backfilled index or total would overflow.
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<bool, Error> {
+            self.ensure_owner()?;
+
+            if self.get_storage_version() >= STORAGE_VERSION {
+                return Ok(true)
+            }
+
+            let start = self.migration_cursor.get().unwrap_or_default();
+            let end = start
+                .saturating_add(MAX_BATCH_SIZE as u64)
+                .min(self.contract_count);
+            for id in start..end {
+                self.backfill_contract(id)?;
+            }
+            self.migration_cursor.set(&end);
+
+            if end < self.contract_count {
+                return Ok(false)
+            }
+            self.storage_version.set(&STORAGE_VERSION);
+            self.migration_cursor.set(&0);
+            Self::env().emit_event(Migrated { version: STORAGE_VERSION });
+
+            Ok(true)
+        }
+
+        /// Adds accounts holding tokens to the holders `audit` sums balances over.
+        ///
+        /// Code that predates the holder list kept balances only, so accounts that
+        /// never bought or sold are not found by `migrate`. They can be read off the
+        /// `Transfer` events and backfilled here by anyone. Accounts without tokens
+        /// or already tracked are skipped.
+        ///
+        /// # Errors
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` accounts are given.
+        ///
+        /// Returns Overflow if the holder count would overflow.
+        #[ink(message)]
+        pub fn backfill_holders(&mut self, accounts: Vec<AccountId>) -> Result<(), Error> {
+            if accounts.len() > MAX_BATCH_SIZE {
+                return Err(Error::BatchTooLarge)
+            }
+            for account in accounts {
+                if self.balance_of_impl(&account) > 0 {
+                    self.track_holder(&account)?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Indexes a contract listed before the indexes existed, using only its
+        /// per-field entries. Does nothing if the contract is already indexed.
+        fn backfill_contract(&mut self, id: ContractId) -> Result<(), Error> {
+            if self.status_position.contains(id) {
+                return Ok(())
+            }
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Ok(())
+            };
+            let total = self.total.get(id).unwrap_or_default();
+            if !self.quantity.contains(id) {
+                let amount = self.weight.get(id).unwrap_or_default();
+                self.quantity.insert(id, &Quantity { amount, unit: Unit::Grams });
+            }
+            self.track_holder(&seller)?;
+            index_push(&mut self.seller_contracts, &mut self.seller_contract_count, seller, id)?;
+
+            let buyer = match self.buyer.get(id) {
+                Some(p) => p,
+                None if self.cancelled.get(id) == Some(true) => {
+                    return self.move_status(id, None, ContractStatus::Cancelled)
+                }
+                None => {
+                    self.move_status(id, None, ContractStatus::Open)?;
+                    return self.update_exposure(seller, |exposure| {
+                        exposure.open_listings = exposure.open_listings.checked_add(1)?;
+                        Some(())
+                    })
+                }
+            };
+            self.track_holder(&buyer)?;
+            index_push(&mut self.buyer_contracts, &mut self.buyer_contract_count, buyer, id)?;
+            if self.finalized.get(id).unwrap_or_default() {
+                return self.move_status(id, None, ContractStatus::Finalized)
+            }
+
+            self.move_status(id, None, ContractStatus::Bought)?;
+            self.track_holder(&self.env().account_id())?;
+            self.hold_escrow(id, total)?;
+            let pooled = self.pooled(id, total);
+            self.update_exposure(seller, |exposure| {
+                exposure.receivable = exposure.receivable.checked_add(pooled)?;
+                Some(())
+            })?;
+            self.update_exposure(buyer, |exposure| {
+                exposure.open_positions = exposure.open_positions.checked_add(1)?;
+                exposure.locked = exposure.locked.checked_add(pooled)?;
+                Some(())
+            })
+        }
+
+        /// Sets the protocol fees charged from now on and the treasury they are
+        /// paid to. Setting both fees to `0` stops charging them.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns InvalidProtocolFee if either fee exceeds `MAX_PROTOCOL_FEE`.
+        #[ink(message)]
+        pub fn set_protocol_fees(
+            &mut self,
+            purchase_fee: BasisPoints,
+            settlement_fee: BasisPoints,
+            treasury: AccountId,
+        ) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if purchase_fee > MAX_PROTOCOL_FEE || settlement_fee > MAX_PROTOCOL_FEE {
+                return Err(Error::InvalidProtocolFee)
+            }
+
+            self.protocol_fees.set(&ProtocolFees {
+                purchase_fee,
+                settlement_fee,
+                treasury,
+            });
+
+            Self::env().emit_event(ProtocolFeesChanged {
+                purchase_fee,
+                settlement_fee,
+                treasury,
+            });
+
+            Ok(())
+        }
+
+        /// Sets the requirements every listing created from now on must meet.
+        /// Listings already open keep the deposit they were created with.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        #[ink(message)]
+        pub fn set_listing_rules(&mut self, rules: ListingRules) -> Result<(), Error> {
+            self.ensure_owner()?;
+
+            self.listing_rules.set(&rules);
+
+            Self::env().emit_event(ListingRulesChanged { rules });
+
+            Ok(())
+        }
+
+        /// Returns the share of `amount` owed to the treasury at `rate`, and the
+        /// treasury, or `0` and no treasury if no fees are set.
+        fn protocol_fee(
+            &self,
+            amount: Balance,
+            rate: impl FnOnce(&ProtocolFees) -> BasisPoints,
+        ) -> (Balance, Option<AccountId>) {
+            match self.protocol_fees.get() {
+                Some(fees) => (
+                    proportion(amount, Balance::from(rate(&fees)), Balance::from(MAX_BASIS_POINTS)),
+                    Some(fees.treasury),
+                ),
+                None => (0, None),
+            }
+        }
+
+        /// Appends series exported from another deployment to the registry, in order,
+        /// so imported contracts keep referencing the same SeriesId.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns ImportClosed if the contract is not being seeded from a snapshot.
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` entries are given.
+        ///
+        /// Returns Overflow if no SeriesId is left to assign.
+        #[ink(message)]
+        pub fn import_series(&mut self, entries: Vec<Series>) -> Result<(), Error> {
+            self.ensure_importer(entries.len())?;
+
+            for entry in entries {
+                let series_id = self.get_series_count();
+                self.series_count.set(&series_id.checked_add(1).ok_or(Error::Overflow)?);
+                self.series_entry.insert(series_id, &entry);
+            }
+
+            Ok(())
+        }
+
+        /// Recreates contracts exported with `export_contracts`, keeping their ids.
+        ///
+        /// Records must be given in ascending order of id, after the contracts
+        /// already imported. Gaps left by archived contracts are allowed. The `total`
+        /// of every bought contract that has not settled, and the deposit of every
+        /// open listing, is minted to the contract account and held again.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns ImportClosed if the contract is not being seeded from a snapshot.
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` records are given.
+        ///
+        /// Returns InvalidSnapshot if a record's ContractId is not above every id
+        /// imported so far, or if it has no seller.
+        ///
+        /// Returns Overflow if the re-minted escrow overflows the total supply.
+        #[ink(message)]
+        pub fn import_contracts(
+            &mut self,
+            records: Vec<(ContractId, CommodityContract)>,
+        ) -> Result<(), Error> {
+            self.ensure_importer(records.len())?;
+
+            for (id, contract) in records {
+                if id < self.contract_count || contract.seller.is_none() {
+                    return Err(Error::InvalidSnapshot)
+                }
+                self.import_contract(id, contract)?;
+                self.contract_count = id.checked_add(1).ok_or(Error::Overflow)?;
+            }
+
+            Ok(())
+        }
+
+        /// Restores the commitments of contracts exported with `export_archives`.
+        ///
+        /// Import archives after every contract, so that the ids of the contracts
+        /// imported later are not taken.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns ImportClosed if the contract is not being seeded from a snapshot.
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` records are given.
+        ///
+        /// Returns InvalidSnapshot if a contract or archive already exists under the id.
+        ///
+        /// Returns Overflow if no ContractId is left after the id.
+        #[ink(message)]
+        pub fn import_archives(&mut self, records: Vec<(ContractId, Hash)>) -> Result<(), Error> {
+            self.ensure_importer(records.len())?;
+
+            for (id, commitment) in records {
+                if self.seller.contains(id) || self.archive.contains(id) {
+                    return Err(Error::InvalidSnapshot)
+                }
+                self.archive.insert(id, &commitment);
+                let next_id = id.checked_add(1).ok_or(Error::Overflow)?;
+                self.contract_count = self.contract_count.max(next_id);
+            }
+
+            Ok(())
+        }
+
+        /// Credits token balances exported with `export_balances`, minting them.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns ImportClosed if the contract is not being seeded from a snapshot.
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` records are given.
+        ///
+        /// Returns InvalidSnapshot if an account already holds tokens.
+        ///
+        /// Returns Overflow if the total supply would overflow.
+        #[ink(message)]
+        pub fn import_balances(&mut self, records: Vec<(AccountId, Balance)>) -> Result<(), Error> {
+            self.ensure_importer(records.len())?;
+
+            for (account, balance) in records {
+                if self.balance_of_impl(&account) > 0 {
+                    return Err(Error::InvalidSnapshot)
+                }
+                self.mint_impl(&account, balance)?;
+            }
+
+            Ok(())
+        }
+
+        /// Seals a contract seeded from a snapshot. Every `import_*` message is
+        /// rejected afterwards.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        ///
+        /// Returns ImportClosed if the contract is not being seeded from a snapshot.
+        #[ink(message)]
+        pub fn finish_import(&mut self) -> Result<(), Error> {
+            self.ensure_importer(0)?;
+
+            self.importing.set(&false);
+
+            Self::env().emit_event(ImportFinished {
+                contract_count: self.contract_count,
+                total_supply: self.total_supply,
+            });
+
+            Ok(())
+        }
+
+        /// Guards every `import_*` message.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner, ImportClosed or BatchTooLarge as documented on
+        /// `import_contracts`.
+        fn ensure_importer(&self, records: usize) -> Result<(), Error> {
+            self.ensure_owner()?;
+            if !self.is_importing() {
+                return Err(Error::ImportClosed)
+            }
+            if records > MAX_BATCH_SIZE {
+                return Err(Error::BatchTooLarge)
+            }
+            Ok(())
+        }
+
+        /// Writes an imported contract into the per-field mappings and indexes it.
+        fn import_contract(&mut self, id: ContractId, contract: CommodityContract) -> Result<(), Error> {
+            let CommodityContract {
+                series,
+                seller,
+                buyer,
+                price,
+                total,
+                weight,
+                quantity,
+                unit_price,
+                finality_block,
+                finalized,
+                quality_spec,
+                quality_schedule,
+                quality_reading,
+                inspector,
+                inspector_fee,
+                attestation,
+                cancelled,
+                deposit,
+                settlement_asset,
+            } = contract;
+            let seller = seller.ok_or(Error::InvalidSnapshot)?;
+            let total = total.unwrap_or_default();
+            let finalized = finalized.unwrap_or_default();
+
+            if let Some(series) = series {
+                self.series.insert(id, &series);
+                index_push(&mut self.series_contracts, &mut self.series_contract_count, series, id)?;
+            }
+            self.seller.insert(id, &seller);
+            if let Some(buyer) = buyer {
+                self.buyer.insert(id, &buyer);
+            }
+            self.price.insert(id, &price.unwrap_or_default());
+            self.total.insert(id, &total);
+            self.weight.insert(id, &weight.unwrap_or_default());
+            if let Some(quantity) = quantity {
+                self.quantity.insert(id, &quantity);
+            }
+            if let Some(unit_price) = unit_price {
+                self.unit_price.insert(id, &unit_price);
+            }
+            self.finality_block.insert(id, &finality_block.unwrap_or_default());
+            self.finalized.insert(id, &finalized);
+            if let Some(spec) = quality_spec {
+                self.quality_spec.insert(id, &spec);
+            }
+            if let Some(schedule) = quality_schedule {
+                self.quality_schedule.insert(id, &schedule);
+            }
+            if let Some(reading) = quality_reading {
+                self.quality_reading.insert(id, &reading);
+            }
+            if let Some(inspector) = inspector {
+                self.inspector.insert(id, &inspector);
+            }
+            if let Some(fee) = inspector_fee {
+                self.inspector_fee.insert(id, &fee);
+            }
+            if let Some(attestation) = attestation {
+                self.attestation.insert(id, &attestation);
+            }
+            if cancelled == Some(true) {
+                self.cancelled.insert(id, &true);
+            }
+            if let Some(asset) = settlement_asset.filter(|asset| *asset != SettlementAsset::Internal) {
+                self.settlement_asset.insert(id, &asset);
+            }
+
+            // Hold the escrow of unsettled contracts again. Escrow held in a PSP22
+            // token or the native currency has to be moved to this contract separately
+            if buyer.is_some() && !finalized {
+                self.mint_impl(&self.env().account_id(), self.pooled(id, total))?;
+            }
+            // Hold the deposit of open listings again
+            let deposit = deposit.unwrap_or_default();
+            if deposit > 0 {
+                let total_deposits = self.total_deposits().checked_add(deposit).ok_or(Error::Overflow)?;
+                self.mint_impl(&self.env().account_id(), deposit)?;
+                self.deposit.insert(id, &deposit);
+                self.total_deposits.set(&total_deposits);
+            }
+            self.backfill_contract(id)
+        }
+
+        /// Creates `value` tokens on the account of `to`.
+        ///
+        /// # Errors
+        ///
+        /// Returns CapExceeded if the total supply would exceed the supply cap.
+        ///
+        /// Returns Overflow if the total supply or the balance of `to` would overflow.
+        fn mint_impl(&mut self, to: &AccountId, value: Balance) -> Result<(), Error> {
+            let total_supply = self.total_supply.checked_add(value).ok_or(Error::Overflow)?;
+            if self.get_supply_cap().is_some_and(|cap| total_supply > cap) {
+                return Err(Error::CapExceeded)
+            }
+            let balance = self.balance_of_impl(to).checked_add(value).ok_or(Error::Overflow)?;
+            self.track_holder(to)?;
+            self.total_supply = total_supply;
+            self.balances.insert(to, &balance);
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(*to),
+                value,
+            });
+            Ok(())
+        }
+
+        /// Destroys `value` tokens on the account of `from`.
+        ///
+        /// The contract account is refused outright: its balance pools the escrow
+        /// and deposits of every contract, which must never leave the supply.
+        ///
+        /// # Errors
+        ///
+        /// Returns PoolNotBurnable if `from` is the contract account.
+        ///
+        /// Returns InsufficientBalance if `from` holds fewer than `value` tokens.
+        ///
+        /// Returns Overflow if `value` exceeds the total supply, which only a corrupt
+        /// ledger allows.
+        fn burn_impl(&mut self, from: &AccountId, value: Balance) -> Result<(), Error> {
+            if *from == self.env().account_id() {
+                return Err(Error::PoolNotBurnable)
+            }
+            let balance = self
+                .balance_of_impl(from)
+                .checked_sub(value)
+                .ok_or(Error::InsufficientBalance)?;
+            let total_supply = self.total_supply.checked_sub(value).ok_or(Error::Overflow)?;
+            self.balances.insert(from, &balance);
+            self.total_supply = total_supply;
+            self.env().emit_event(Transfer {
+                from: Some(*from),
+                to: None,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Gives `role` to `account`, emitting RoleGranted if it did not hold it.
+        fn add_role(&mut self, role: Role, account: AccountId) {
+            if !self.has_role(role, account) {
+                self.role_members.insert((role, account), &());
+                Self::env().emit_event(RoleGranted {
+                    role,
+                    account,
+                    sender: self.env().caller(),
+                });
+            }
+        }
+
+        /// Removes `role` from `account`, emitting RoleRevoked if it held it.
+        fn remove_role(&mut self, role: Role, account: AccountId) {
+            if self.has_role(role, account) {
+                self.role_members.remove((role, account));
+                Self::env().emit_event(RoleRevoked {
+                    role,
+                    account,
+                    sender: self.env().caller(),
+                });
+            }
+        }
+
+        /// Guards every message restricted to the members of `role`.
+        ///
+        /// # Errors
+        ///
+        /// Returns MissingRole if the caller does not hold `role`.
+        fn ensure_role(&self, role: Role) -> Result<(), Error> {
+            if !self.has_role(role, self.env().caller()) {
+                return Err(Error::MissingRole)
+            }
+            Ok(())
+        }
+
+        /// Guards the role messages against `Admin`, which is held by the owner
+        /// alone so that roles and ownership cannot drift apart.
+        ///
+        /// # Errors
+        ///
+        /// Returns AdminRoleReserved if `role` is `Admin`.
+        fn ensure_delegable(role: Role) -> Result<(), Error> {
+            if role == Role::Admin {
+                return Err(Error::AdminRoleReserved)
+            }
+            Ok(())
+        }
+
+        /// Guards every administrative message.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotOwner if the caller is not the owner.
+        fn ensure_owner(&self) -> Result<(), Error> {
+            if Some(self.env().caller()) != self.get_owner() {
+                return Err(Error::NotOwner)
+            }
+            Ok(())
+        }
+
+        /// Applies `update` to the exposure of `account`.
+        ///
+        /// # Errors
+        ///
+        /// Returns Overflow if `update` returns `None`.
+        fn update_exposure(
+            &mut self,
+            account: AccountId,
+            update: impl FnOnce(&mut Exposure) -> Option<()>,
+        ) -> Result<(), Error> {
+            let mut exposure = self.exposure.get(account).unwrap_or_default();
+            update(&mut exposure).ok_or(Error::Overflow)?;
+            self.exposure.insert(account, &exposure);
+            Ok(())
+        }
+
+        /// Moves a contract from the status index of `from` to the one of `to`.
+        ///
+        /// The contract is swapped out with the last contract of `from` so the
+        /// index stays dense.
+        fn move_status(
+            &mut self,
+            id: ContractId,
+            from: Option<ContractStatus>,
+            to: ContractStatus,
+        ) -> Result<(), Error> {
+            if let Some(from) = from {
+                self.remove_status(id, from)?;
+            }
+            let position = self.status_contract_count.get(to).unwrap_or_default();
+            self.status_position.insert(id, &position);
+            index_push(&mut self.status_contracts, &mut self.status_contract_count, to, id)
+        }
+
+        /// Removes a contract from the contracts in `from`, swapping the last
+        /// contract of that status into its position.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if the contract or the last contract of `from`
+        /// is missing from the index, which only inconsistent migrated or imported
+        /// state allows.
+        ///
+        /// Returns Overflow if the index of `from` is empty.
+        fn remove_status(&mut self, id: ContractId, from: ContractStatus) -> Result<(), Error> {
+            let position = self.status_position.get(id).ok_or(Error::ContractNotFound)?;
+            let last = self
+                .status_contract_count
+                .get(from)
+                .unwrap_or_default()
+                .checked_sub(1)
+                .ok_or(Error::Overflow)?;
+            if position > last {
+                return Err(Error::ContractNotFound)
+            }
+            if position != last {
+                let moved = self.status_contracts.get((from, last)).ok_or(Error::ContractNotFound)?;
+                self.status_contracts.insert((from, position), &moved);
+                self.status_position.insert(moved, &position);
+            }
+            self.status_contracts.remove((from, last));
+            self.status_contract_count.insert(from, &last);
+            self.status_position.remove(id);
+            Ok(())
+        }
+
+        /// Creates a new Ssal Contract
+        /// 
+        /// Adds inputted data to the relevant mappings, increments the contract 
+        /// count, and adds the caller as the seller for the contract.
+        ///
+        /// Moves the listing deposit set by the listing rules from the caller to
+        /// the contract account until the contract is bought, cancelled or expires.
+        /// 
+        /// # Errors
+        /// 
+        /// Returns Paused if listing is paused.
+        ///
+        /// Returns SellerNotVerified if the seller policy requires the
+        /// `VerifiedSeller` role and the caller does not hold it.
+        ///
+        /// Returns SeriesNotFound if `_series` is not in the registry.
+        ///
+        /// Returns SeriesRetired if `_series` no longer accepts listings.
+        ///
+        /// Returns InvalidBlockNumber if the inputted finality_block is less than 
+        /// the current block number.
+        ///
+        /// Returns WeightTooLow, TotalTooLow or FinalityTooFar if `_weight`,
+        /// `_total` or `_finality_block` break the listing rules.
+        ///
+        /// Returns InsufficientBalance if the caller cannot put up the listing
+        /// deposit.
+        ///
+        /// Returns Overflow if no ContractId is left to assign.
+        #[ink(message)]
+        pub fn create_contract(
+            &mut self,
+            _series: SeriesId,
+            _price: Balance,
+            _total: Balance,
+            _weight: Grams,
+            _finality_block: BlockNumber
+        ) -> Result<ContractId, Error> {
+            let quantity = Quantity { amount: _weight, unit: Unit::Grams };
+            self.create_contract_impl(_series, _price, _total, quantity, None, _finality_block)
+        }
+
+        /// Creates a new Ssal Contract priced per unit of quantity.
+        ///
+        /// The total is derived as `unit_price` times the amount of `quantity`, and
+        /// the weight is the quantity converted to grams. Since `unit_price` is
+        /// quoted per unit of the series, `quantity` must be given in that unit.
+        ///
+        /// # Errors
+        ///
+        /// Returns InvalidUnit if `quantity` is given in sacks of zero grams.
+        ///
+        /// Returns UnitMismatch if `quantity` is not given in the unit of `series`.
+        ///
+        /// Returns Overflow if the quantity in grams or the derived total
+        /// overflows.
+        ///
+        /// Otherwise returns the same errors as `create_contract`.
+        #[ink(message)]
+        pub fn create_contract_per_unit(
+            &mut self,
+            series: SeriesId,
+            price: Balance,
+            unit_price: Balance,
+            quantity: Quantity,
+            finality_block: BlockNumber,
+        ) -> Result<ContractId, Error> {
+            let total = unit_price
+                .checked_mul(Balance::from(quantity.amount))
+                .ok_or(Error::Overflow)?;
+            self.create_contract_impl(series, price, total, quantity, Some(unit_price), finality_block)
+        }
+
+        /// Creates many Ssal Contracts at once, returning their ContractIds in order.
+        ///
+        /// Every listing is validated before any is created, together with the
+        /// combined effect of the batch on the ids, indexes, deposits and exposure
+        /// it adds to, so either all lots are listed or none are. Should a listing
+        /// still fail midway, the returned error reverts the listings created
+        /// before it.
+        ///
+        /// # Errors
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` listings are given.
+        ///
+        /// Returns InsufficientBalance if the caller cannot put up the listing
+        /// deposit of every listing.
+        ///
+        /// Returns Overflow if not enough ContractIds are left to assign, or if the
+        /// batch would overflow an index, the deposits held or the caller's
+        /// exposure.
+        ///
+        /// Otherwise returns the first error `create_contract` would return for
+        /// any of the listings.
+        #[ink(message)]
+        pub fn create_contracts(
+            &mut self,
+            listings: Vec<ListingParams>,
+        ) -> Result<Vec<ContractId>, Error> {
+            if listings.len() > MAX_BATCH_SIZE {
+                return Err(Error::BatchTooLarge)
+            }
+            // Validate every listing up front so the batch lists all lots or none
+            for listing in &listings {
+                let quantity = Quantity { amount: listing.weight, unit: Unit::Grams };
+                self.check_listing(listing.series, listing.total, quantity, false, listing.finality_block)?;
+            }
+            // Check that the indexes, deposits and exposure fit the whole batch
+            let caller = self.env().caller();
+            let count = listings.len() as u64;
+            self.contract_count
+                .checked_add(count)
+                .ok_or(Error::Overflow)?;
+            for (i, listing) in listings.iter().enumerate() {
+                let earlier = listings[..i].iter().filter(|l| l.series == listing.series).count();
+                self.series_contract_count
+                    .get(listing.series)
+                    .unwrap_or_default()
+                    .checked_add(earlier as u64 + 1)
+                    .ok_or(Error::Overflow)?;
+            }
+            self.seller_contract_count
+                .get(caller)
+                .unwrap_or_default()
+                .checked_add(count)
+                .ok_or(Error::Overflow)?;
+            self.status_contract_count
+                .get(ContractStatus::Open)
+                .unwrap_or_default()
+                .checked_add(count)
+                .ok_or(Error::Overflow)?;
+            self.exposure
+                .get(caller)
+                .unwrap_or_default()
+                .open_listings
+                .checked_add(count)
+                .ok_or(Error::Overflow)?;
+            let deposits = self
+                .get_listing_rules()
+                .deposit
+                .checked_mul(listings.len() as Balance)
+                .ok_or(Error::Overflow)?;
+            self.total_deposits().checked_add(deposits).ok_or(Error::Overflow)?;
+            if self.balance_of_impl(&caller) < deposits {
+                return Err(Error::InsufficientBalance)
+            }
+
+            listings
+                .iter()
+                .map(|listing| {
+                    self.create_contract(
+                        listing.series,
+                        listing.price,
+                        listing.total,
+                        listing.weight,
+                        listing.finality_block,
+                    )
+                })
+                .collect()
+        }
+
+        /// Validates a listing and returns its quantity normalized to grams.
+        ///
+        /// Plain listings give the weight of the lot in grams, which need not be a
+        /// whole number of series units, so only listings priced `per_unit` must
+        /// match the unit of their series.
+        ///
+        /// # Errors
+        ///
+        /// Returns Paused, SellerNotVerified, SeriesNotFound, SeriesRetired,
+        /// InvalidBlockNumber, WeightTooLow, TotalTooLow, FinalityTooFar or Overflow
+        /// as documented on `create_contract`, and InvalidUnit or UnitMismatch as
+        /// documented on `create_contract_per_unit`.
+        fn check_listing(
+            &self,
+            series: SeriesId,
+            total: Balance,
+            quantity: Quantity,
+            per_unit: bool,
+            finality_block: BlockNumber,
+        ) -> Result<Grams, Error> {
+            self.ensure_unpaused(PauseScope::Listing)?;
+            // Check that the seller may list under the current policy
+            if self.requires_verified_seller() && !self.has_role(Role::VerifiedSeller, self.env().caller()) {
+                return Err(Error::SellerNotVerified)
+            }
+            // Check that series accepts listings
+            let entry = match self.series_entry.get(series) {
+                Some(entry) if entry.active => entry,
+                Some(_) => return Err(Error::SeriesRetired),
+                None => return Err(Error::SeriesNotFound)
+            };
+            // Check that the quantity is in a usable unit
+            if quantity.unit.grams() == 0 {
+                return Err(Error::InvalidUnit)
+            }
+            if per_unit && quantity.unit != entry.unit {
+                return Err(Error::UnitMismatch)
+            }
+            // Check that finality block is valid
+            if finality_block < self.env().block_number(){
+                return Err(Error::InvalidBlockNumber);
+            }
+            // Normalize quantity to grams
+            let weight = quantity.to_grams().ok_or(Error::Overflow)?;
+
+            // Check the listing against the listing rules
+            let rules = self.get_listing_rules();
+            if weight < rules.min_weight {
+                return Err(Error::WeightTooLow)
+            }
+            if total < rules.min_total {
+                return Err(Error::TotalTooLow)
+            }
+            let horizon = finality_block - self.env().block_number();
+            if rules.max_finality_horizon.is_some_and(|max| horizon > max) {
+                return Err(Error::FinalityTooFar)
+            }
+            Ok(weight)
+        }
+
+        /// Shared listing logic of `create_contract` and `create_contract_per_unit`.
+        fn create_contract_impl(
+            &mut self,
+            _series: SeriesId,
+            _price: Balance,
+            _total: Balance,
+            _quantity: Quantity,
+            _unit_price: Option<Balance>,
+            _finality_block: BlockNumber
+        ) -> Result<ContractId, Error> {
+            let _weight = self.check_listing(
+                _series,
+                _total,
+                _quantity,
+                _unit_price.is_some(),
+                _finality_block,
+            )?;
+            // Reserve the contract's id
+            let id = self.contract_count;
+            let next_id = id.checked_add(1).ok_or(Error::Overflow)?;
+
+            let caller = self.env().caller();
+            // Hold the listing deposit until the contract is bought, cancelled or expires
+            let deposit = self.get_listing_rules().deposit;
+            if deposit > 0 {
+                let total_deposits = self.total_deposits().checked_add(deposit).ok_or(Error::Overflow)?;
+                self.transfer_from_to(&caller, &self.env().account_id(), deposit)?;
+                self.deposit.insert(id, &deposit);
+                self.total_deposits.set(&total_deposits);
+            }
+            // Set contract data into relevant mappings
+            self.series.insert(id, &_series);
+            self.seller.insert(id, &caller);
+            self.price.insert(id, &_price);
+            self.total.insert(id, &_total);
+            self.weight.insert(id, &_weight);
+            self.quantity.insert(id, &_quantity);
+            if let Some(unit_price) = _unit_price {
+                self.unit_price.insert(id, &unit_price);
+            }
+            self.finality_block.insert(id, &_finality_block);
+            self.finalized.insert(id, &false);
+
+            // Index contract under its series, seller and status
+            index_push(&mut self.series_contracts, &mut self.series_contract_count, _series, id)?;
+            index_push(&mut self.seller_contracts, &mut self.seller_contract_count, caller, id)?;
+            self.move_status(id, None, ContractStatus::Open)?;
+            self.update_exposure(caller, |exposure| {
+                exposure.open_listings = exposure.open_listings.checked_add(1)?;
+                Some(())
+            })?;
+
+            self.contract_count = next_id;
+
+            Self::env().emit_event(NewContract {
+                contract_id: id,
+                series: _series,
+                seller: caller,
+                price: _price,
+                total: _total,
+                weight: _weight,
+                quantity: _quantity,
+                finality_block: _finality_block,
+            });
+
+            Ok(id)
+        }
+
+        /// Withdraws a listing nobody has bought yet and returns its deposit.
+        ///
+        /// Only the seller can call this function.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        ///
+        /// Returns OnlySellerCanModify if a caller other than the seller attempts
+        /// to cancel the listing.
+        ///
+        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
+        ///
+        /// Returns ContractCancelled if the listing was already withdrawn or expired.
+        #[ink(message)]
+        pub fn cancel_contract(&mut self, id: ContractId) -> Result<(), Error> {
+            let seller = self.check_cancel(id)?;
+            if self.env().caller() != seller {
+                return Err(Error::OnlySellerCanModify)
+            }
+            self.cancel(id, seller, false)
+        }
+
+        /// Closes a listing nobody bought before its finality block passed and
+        /// returns its deposit to the seller.
+        ///
+        /// Anyone can call this function.
+        ///
+        /// # Errors
+        ///
+        /// Returns ListingNotExpired if the finality block has not passed yet.
+        ///
+        /// Otherwise returns the same errors as `cancel_contract`, except
+        /// OnlySellerCanModify.
+        #[ink(message)]
+        pub fn expire_contract(&mut self, id: ContractId) -> Result<(), Error> {
+            let seller = self.check_cancel(id)?;
+            if self.finality_block.get(id).unwrap() >= self.env().block_number() {
+                return Err(Error::ListingNotExpired)
+            }
+            self.cancel(id, seller, true)
+        }
+
+        /// Checks that a listing can be cancelled and returns its seller.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound, ContractAlreadyBought or ContractCancelled as
+        /// documented on `cancel_contract`.
+        fn check_cancel(&self, id: ContractId) -> Result<AccountId, Error> {
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            if self.buyer.get(id).is_some() {
+                return Err(Error::ContractAlreadyBought)
+            }
+            if self.cancelled.get(id) == Some(true) {
+                return Err(Error::ContractCancelled)
+            }
+            Ok(seller)
+        }
+
+        /// Shared logic of `cancel_contract` and `expire_contract`.
+        fn cancel(&mut self, id: ContractId, seller: AccountId, expired: bool) -> Result<(), Error> {
+            let deposit = self.release_deposit(id, &seller)?;
+            self.cancelled.insert(id, &true);
+            self.closed_at.insert(id, &self.env().block_number());
+            self.move_status(id, Some(ContractStatus::Open), ContractStatus::Cancelled)?;
+            self.update_exposure(seller, |exposure| {
+                exposure.open_listings = exposure.open_listings.checked_sub(1)?;
+                Some(())
+            })?;
+
+            Self::env().emit_event(ContractCancelled {
+                contract_id: id,
+                seller,
+                expired,
+                deposit,
+            });
+
+            Ok(())
+        }
+
+        /// Returns the listing deposit of a contract to its seller, if it has one,
+        /// and returns the amount.
+        fn release_deposit(&mut self, id: ContractId, seller: &AccountId) -> Result<Balance, Error> {
+            let deposit = self.deposit.take(id).unwrap_or_default();
+            if deposit > 0 {
+                let total_deposits = self.total_deposits().checked_sub(deposit).ok_or(Error::Overflow)?;
+                self.total_deposits.set(&total_deposits);
+                self.transfer_from_to(&self.env().account_id(), seller, deposit)?;
+            }
+            Ok(deposit)
+        }
+
+        /// Removes a finalized or cancelled contract from storage once
+        /// `ARCHIVE_RETENTION` blocks have passed since it closed.
+        ///
+        /// Every per-contract entry is removed and only a hash of the final record
+        /// is kept, so the record carried by the `ContractArchived` event can be
+        /// proven later. The storage deposit freed by the removal is refunded to
+        /// the caller, so anyone can call this function.
+        ///
+        /// Archived contracts are no longer returned by queries, but stay listed in
+        /// the seller, buyer and series indexes.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract,
+        /// including once it is archived.
+        ///
+        /// Returns ContractNotClosed if the contract is neither finalized nor cancelled.
+        ///
+        /// Returns RetentionPeriodNotOver if fewer than `ARCHIVE_RETENTION` blocks
+        /// have passed since the contract closed.
+        #[ink(message)]
+        pub fn archive_contract(&mut self, id: ContractId) -> Result<(), Error> {
+            let record = self.get_contract(id)?;
+            let status = match self.get_status(id) {
+                Some(status @ (ContractStatus::Finalized | ContractStatus::Cancelled)) => status,
+                _ => return Err(Error::ContractNotClosed)
+            };
+            // Contracts closed before closing blocks were recorded count from genesis
+            let closed_at = self.closed_at.get(id).unwrap_or_default();
+            if self.env().block_number() < closed_at.saturating_add(ARCHIVE_RETENTION) {
+                return Err(Error::RetentionPeriodNotOver)
+            }
+
+            self.remove_status(id, status)?;
+            self.series.remove(id);
+            self.seller.remove(id);
+            self.buyer.remove(id);
+            self.price.remove(id);
+            self.total.remove(id);
+            self.weight.remove(id);
+            self.quantity.remove(id);
+            self.unit_price.remove(id);
+            self.finality_block.remove(id);
+            self.finalized.remove(id);
+            self.quality_spec.remove(id);
+            self.quality_schedule.remove(id);
+            self.quality_reading.remove(id);
+            self.inspector.remove(id);
+            self.inspector_fee.remove(id);
+            self.attestation.remove(id);
+            self.cancelled.remove(id);
+            self.settlement_asset.remove(id);
+            self.closed_at.remove(id);
+
+            let commitment = record.commitment(id);
+            self.archive.insert(id, &commitment);
+
+            Self::env().emit_event(ContractArchived {
+                contract_id: id,
+                record,
+                commitment,
+            });
+
+            Ok(())
+        }
+
+        /// Buy a contract given a ContractId
+        /// 
+        /// Transfers funds (the amount designated by price) from buyer's account to 
+        /// seller's account, less the purchase fee which goes to the treasury.
+        /// 
+        /// Transfers funds (the amount designated by total) to the smart contract account 
+        /// to lockup the buyer's assets.
+        /// 
+        /// Adds buyer's AccountId to the buyer mapping if all the transfers goes through,
+        /// and returns the listing deposit to the seller.
+        ///
+        /// Contracts settling in a PSP22 token pull both amounts from the buyer with
+        /// a single `transfer_from` on the token, so the buyer must approve this
+        /// contract to spend them first.
+        /// 
+        /// # Errors
+        /// 
+        /// Returns Paused if buying is paused.
+        ///
+        /// Returns ContractNotFound if the seller cannot be found for the given contract.
+        /// 
+        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
+        /// 
+        /// Returns ContractCancelled if the listing was withdrawn or expired.
+        ///
+        /// Returns InvalidInspector if the caller is the inspector of the contract.
+        ///
+        /// Returns Insufficient Balance if the buyer does not have enough funds to
+        /// cover both the contract price and total price of the product.
+        ///
+        /// Returns Overflow if the contract price and total price add up to more
+        /// than a `Balance` can hold.
+        ///
+        /// Returns TokenTransferFailed if the PSP22 token of the contract rejects
+        /// a transfer, e.g. for lack of balance or allowance.
+        ///
+        /// Returns SettlementAssetMismatch if the contract settles in the native
+        /// currency, which `buy_contract_native` takes.
+        #[ink(message)]
+        pub fn buy_contract(&mut self, id: ContractId) -> Result<(), Error> {
+            let (seller, price, total) = self.check_buy(id)?;
+
+            // Fetch transactional variables
+            let caller = self.env().caller();
+            let pool = self.env().account_id();
+            let asset = self.settlement_asset_of(id);
+            let cost = price.checked_add(total).ok_or(Error::Overflow)?;
+            let payer = match asset {
+                // Check caller has enough money
+                SettlementAsset::Internal => {
+                    if self.balance_of(caller) < cost {
+                        return Err(Error::InsufficientBalance)
+                    }
+                    caller
+                }
+                // Pull the whole cost in one call, so a rejected payment leaves
+                // nothing half done
+                SettlementAsset::Psp22(_) => {
+                    self.pay(asset, &caller, &pool, cost)?;
+                    pool
+                }
+                SettlementAsset::Native => return Err(Error::SettlementAssetMismatch),
+            };
+            self.buy_impl(id, seller, price, total, payer)
+        }
+
+        /// Buy a contract settling in the native currency given a ContractId
+        ///
+        /// The caller sends exactly the price plus the total with the call. The
+        /// seller is paid the price, less the purchase fee which goes to the
+        /// treasury, and the total is held in escrow on the contract account.
+        ///
+        /// Payouts the runtime rejects are held for their recipient to claim with
+        /// `claim_native` or `claim_native_to`.
+        ///
+        /// # Errors
+        ///
+        /// Returns SettlementAssetMismatch if the contract does not settle in the
+        /// native currency.
+        ///
+        /// Returns InvalidPayment if the value sent differs from the price plus total.
+        ///
+        /// Otherwise returns the same errors as `buy_contract`.
+        #[ink(message, payable)]
+        pub fn buy_contract_native(&mut self, id: ContractId) -> Result<(), Error> {
+            let (seller, price, total) = self.check_buy(id)?;
+            if self.settlement_asset_of(id) != SettlementAsset::Native {
+                return Err(Error::SettlementAssetMismatch)
+            }
+            let cost = price.checked_add(total).ok_or(Error::Overflow)?;
+            if self.env().transferred_value() != cost {
+                return Err(Error::InvalidPayment)
+            }
+            self.buy_impl(id, seller, price, total, self.env().account_id())
+        }
+
+        /// Shared purchase logic of `buy_contract` and `buy_contract_native`, paying
+        /// the seller and escrow out of `payer`'s funds.
+        fn buy_impl(
+            &mut self,
+            id: ContractId,
+            seller: AccountId,
+            price: Balance,
+            total: Balance,
+            payer: AccountId,
+        ) -> Result<(), Error> {
+            let caller = self.env().caller();
+            let pool = self.env().account_id();
+            let asset = self.settlement_asset_of(id);
+
+            // Run transfers, withholding the purchase fee, and record the escrow
+            let (protocol_fee, treasury) = self.protocol_fee(price, |fees| fees.purchase_fee);
+            self.pay(asset, &payer, &seller, price - protocol_fee)?;
+            if let Some(treasury) = treasury.filter(|_| protocol_fee > 0) {
+                self.pay(asset, &payer, &treasury, protocol_fee)?;
+            }
+            if payer != pool {
+                self.pay(asset, &payer, &pool, total)?;
+            }
+            self.hold_escrow(id, total)?;
+            let pooled = self.pooled(id, total);
+            self.release_deposit(id, &seller)?;
+            // Add buyer to the relevant mapping
+            self.buyer.insert(id, &caller);
+            index_push(&mut self.buyer_contracts, &mut self.buyer_contract_count, caller, id)?;
+            self.move_status(id, Some(ContractStatus::Open), ContractStatus::Bought)?;
+            self.update_exposure(seller, |exposure| {
+                exposure.open_listings = exposure.open_listings.checked_sub(1)?;
+                exposure.receivable = exposure.receivable.checked_add(pooled)?;
+                Some(())
+            })?;
+            self.update_exposure(caller, |exposure| {
+                exposure.open_positions = exposure.open_positions.checked_add(1)?;
+                exposure.locked = exposure.locked.checked_add(pooled)?;
+                Some(())
+            })?;
+
+            Self::env().emit_event(ContractBought {
+                contract_id: id,
+                buyer: caller,
+                price,
+                total,
+                protocol_fee,
+            });
+
+            Ok(())
+        }
+
+        /// Records `total` as the escrow of a contract and adds it to the sum held
+        /// in its settlement asset.
+        fn hold_escrow(&mut self, id: ContractId, total: Balance) -> Result<(), Error> {
+            match self.settlement_asset_of(id) {
+                SettlementAsset::Internal => {
+                    let total_escrow = self.total_escrow().checked_add(total).ok_or(Error::Overflow)?;
+                    self.total_escrow.set(&total_escrow);
+                }
+                SettlementAsset::Native => {
+                    let native_escrow = self.native_escrow().checked_add(total).ok_or(Error::Overflow)?;
+                    self.native_escrow.set(&native_escrow);
+                }
+                SettlementAsset::Psp22(_) => (),
+            }
+            self.escrow.insert(id, &total);
+            Ok(())
+        }
+
+        /// Removes the escrow of a contract from the sum held in its settlement
+        /// asset and returns it.
+        ///
+        /// # Errors
+        ///
+        /// Returns Overflow if the sum held is smaller than the escrow, which only
+        /// a corrupt ledger allows.
+        fn release_escrow(&mut self, id: ContractId) -> Result<Balance, Error> {
+            let total = self.escrow.take(id).unwrap_or_default();
+            match self.settlement_asset_of(id) {
+                SettlementAsset::Internal => {
+                    let total_escrow = self.total_escrow().checked_sub(total).ok_or(Error::Overflow)?;
+                    self.total_escrow.set(&total_escrow);
+                }
+                SettlementAsset::Native => {
+                    let native_escrow = self.native_escrow().checked_sub(total).ok_or(Error::Overflow)?;
+                    self.native_escrow.set(&native_escrow);
+                }
+                SettlementAsset::Psp22(_) => (),
+            }
+            Ok(total)
+        }
+
+        /// Buys many contracts at once.
+        ///
+        /// In `Atomic` mode every contract is checked and the caller's total
+        /// exposure, the sum of every price and total, is verified once up front.
+        /// Either all contracts are bought or none are.
+        ///
+        /// In `BestEffort` mode each contract is bought on its own and the result of
+        /// every purchase is returned in order.
+        ///
+        /// # Errors
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` ids are given.
+        ///
+        /// In `Atomic` mode, returns ContractAlreadyBought if an id is repeated,
+        /// SettlementAssetMismatch if a contract settles in the native currency,
+        /// InsufficientBalance if the caller cannot cover the total exposure,
+        /// Overflow if the exposure overflows, and otherwise the first error
+        /// `buy_contract` would return for any of the ids.
+        #[ink(message)]
+        pub fn buy_contracts(
+            &mut self,
+            ids: Vec<ContractId>,
+            mode: BatchMode,
+        ) -> Result<Vec<Result<(), Error>>, Error> {
+            if ids.len() > MAX_BATCH_SIZE {
+                return Err(Error::BatchTooLarge)
+            }
+            if mode == BatchMode::BestEffort {
+                return Ok(ids.iter().map(|id| self.buy_contract(*id)).collect())
+            }
+
+            // Check every contract and the caller's total exposure up front
+            let mut exposure: Balance = 0;
+            for (i, id) in ids.iter().enumerate() {
+                if ids[..i].contains(id) {
+                    return Err(Error::ContractAlreadyBought)
+                }
+                let (_, price, total) = self.check_buy(*id)?;
+                match self.settlement_asset_of(*id) {
+                    SettlementAsset::Internal => (),
+                    SettlementAsset::Psp22(_) => continue,
+                    SettlementAsset::Native => return Err(Error::SettlementAssetMismatch),
+                }
+                exposure = exposure
+                    .checked_add(price)
+                    .and_then(|exposure| exposure.checked_add(total))
+                    .ok_or(Error::Overflow)?;
+            }
+            if self.balance_of(self.env().caller()) < exposure {
+                return Err(Error::InsufficientBalance)
+            }
+
+            ids.iter().map(|id| self.buy_contract(*id).map(Ok)).collect()
+        }
+
+        /// Checks that a contract can be bought and returns its seller, price and total.
+        ///
+        /// # Errors
+        ///
+        /// Returns Paused if buying is paused.
+        ///
+        /// Returns ContractNotFound if the seller cannot be found for the given contract.
+        ///
+        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
+        ///
+        /// Returns ContractCancelled if the listing was withdrawn or expired.
+        ///
+        /// Returns InvalidInspector if the caller is the inspector of the contract.
+        fn check_buy(&self, id: ContractId) -> Result<(AccountId, Balance, Balance), Error> {
+            self.ensure_unpaused(PauseScope::Buying)?;
+            // Check wether contract exists
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check whether there is already a buyer.
+            if self.buyer.get(id).is_some() {
+                return Err(Error::ContractAlreadyBought)
+            }
+            if self.cancelled.get(id) == Some(true) {
+                return Err(Error::ContractCancelled)
+            }
+            // The inspector cannot certify a lot it bought
+            if self.inspector.get(id) == Some(self.env().caller()) {
+                return Err(Error::InvalidInspector)
+            }
+            Ok((seller, self.price.get(id).unwrap(), self.total.get(id).unwrap()))
+        }
+
+        /// Attaches a quality spec and premium/discount schedule to a contract.
+        ///
+        /// Only the seller can call this function, and only before the contract has
+        /// been bought so the buyer always purchases under known terms.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        ///
+        /// Returns OnlySellerCanModify if a caller other than the seller attempts
+        /// to set the quality spec.
+        ///
+        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
+        ///
+        /// Returns InvalidQualitySchedule if any rate of the schedule exceeds
+        /// `MAX_BASIS_POINTS`.
+        #[ink(message)]
+        pub fn set_quality(
+            &mut self,
+            id: ContractId,
+            spec: QualitySpec,
+            schedule: QualitySchedule,
+        ) -> Result<(), Error> {
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that seller is caller
+            if self.env().caller() != seller {
+                return Err(Error::OnlySellerCanModify)
+            }
+            // Check that terms can still change
+            if self.buyer.get(id).is_some() {
+                return Err(Error::ContractAlreadyBought)
+            }
+            let rates = [
+                schedule.moisture_discount,
+                schedule.broken_kernel_discount,
+                schedule.grade_premium,
+                schedule.grade_discount,
+                schedule.variety_discount,
+            ];
+            if rates.iter().any(|rate| *rate > MAX_BASIS_POINTS) {
+                return Err(Error::InvalidQualitySchedule)
+            }
+
+            self.quality_spec.insert(id, &spec);
+            self.quality_schedule.insert(id, &schedule);
+
+            Self::env().emit_event(QualitySpecified {
+                contract_id: id,
+                spec,
+                schedule,
+            });
+
+            Ok(())
+        }
+
+        /// Names a neutral inspector who certifies delivery of a contract.
+        ///
+        /// Once an inspector is named, settlement relies on their attestation instead
+        /// of the buyer's word. The inspector earns `fee` out of the escrow when the
+        /// contract settles.
+        ///
+        /// The inspector must hold the Inspector role and cannot be the seller, nor
+        /// later buy the contract, so that neither party certifies its own lot.
+        ///
+        /// Only the seller can call this function, and only before the contract has
+        /// been bought.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        ///
+        /// Returns OnlySellerCanModify if a caller other than the seller attempts
+        /// to name the inspector.
+        ///
+        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
+        ///
+        /// Returns InvalidInspectorFee if `fee` exceeds the total of the contract.
+        ///
+        /// Returns InvalidInspector if `inspector` lacks the Inspector role or is
+        /// the seller.
+        #[ink(message)]
+        pub fn set_inspector(
+            &mut self,
+            id: ContractId,
+            inspector: AccountId,
+            fee: Balance,
+        ) -> Result<(), Error> {
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that seller is caller
+            if self.env().caller() != seller {
+                return Err(Error::OnlySellerCanModify)
+            }
+            // Check that terms can still change
+            if self.buyer.get(id).is_some() {
+                return Err(Error::ContractAlreadyBought)
+            }
+            if fee > self.total.get(id).unwrap() {
+                return Err(Error::InvalidInspectorFee)
+            }
+            if inspector == seller || !self.has_role(Role::Inspector, inspector) {
+                return Err(Error::InvalidInspector)
+            }
+
+            self.inspector.insert(id, &inspector);
+            self.inspector_fee.insert(id, &fee);
+
+            Self::env().emit_event(InspectorAssigned {
+                contract_id: id,
+                inspector,
+                fee,
+            });
+
+            Ok(())
+        }
+
+        /// Picks the asset the price and total of a contract are paid in.
+        ///
+        /// Contracts settle in the internal token unless the seller picks an
+        /// external PSP22 token or the native currency. For a PSP22 token the buyer
+        /// has to approve this contract to spend the price and total on the token
+        /// before buying, and native contracts are bought with `buy_contract_native`.
+        /// Listing deposits are always held in the internal token.
+        ///
+        /// Only the seller can call this function, and only before the contract has
+        /// been bought.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        ///
+        /// Returns OnlySellerCanModify if a caller other than the seller attempts
+        /// to pick the asset.
+        ///
+        /// Returns ContractAlreadyBought if the contract already has a buyer listed.
+        ///
+        /// Returns InvalidSettlementAsset if the token is this contract itself.
+        #[ink(message)]
+        pub fn set_settlement_asset(
+            &mut self,
+            id: ContractId,
+            asset: SettlementAsset,
+        ) -> Result<(), Error> {
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that seller is caller
+            if self.env().caller() != seller {
+                return Err(Error::OnlySellerCanModify)
+            }
+            // Check that terms can still change
+            if self.buyer.get(id).is_some() {
+                return Err(Error::ContractAlreadyBought)
+            }
+            if asset == SettlementAsset::Psp22(self.env().account_id()) {
+                return Err(Error::InvalidSettlementAsset)
+            }
+
+            match asset {
+                SettlementAsset::Internal => self.settlement_asset.remove(id),
+                SettlementAsset::Psp22(_) | SettlementAsset::Native => {
+                    self.settlement_asset.insert(id, &asset);
+                }
+            }
+
+            Self::env().emit_event(SettlementAssetSet {
+                contract_id: id,
+                asset,
+            });
+
+            Ok(())
+        }
+
+        /// Certifies delivery of a contract on behalf of its designated inspector.
+        ///
+        /// Stores the attestation, which is then used to settle the contract in place
+        /// of the buyer's word. Deliveries short of the listed weight are settled pro
+        /// rata and the quality reading, if any, is graded against the contract's spec.
+        ///
+        /// If the finality block has already passed the contract settles right away,
+        /// otherwise anyone can call `finalize` once it has.
+        ///
+        /// # Errors
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        ///
+        /// Returns ContractAlreadyFinalized if the contract has already been finalized.
+        ///
+        /// Returns ContractNotPurchased if the contract has not been bought.
+        ///
+        /// Returns OnlyInspectorCanAttest if the caller is not the designated inspector
+        /// or no longer holds the Inspector role.
+        ///
+        /// Returns AlreadyAttested if the inspector already attested delivery.
+        ///
+        /// Returns QualitySpecNotFound if a reading is given for a contract without a spec.
+        #[ink(message)]
+        pub fn attest_delivery(
+            &mut self,
+            id: ContractId,
+            delivered_grams: Grams,
+            quality_reading: Option<QualityReading>,
+            report_hash: Hash,
+        ) -> Result<(), Error> {
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that it has not already been finalized
+            if self.is_finalized(id).unwrap() {
+                return Err(Error::ContractAlreadyFinalized);
+            }
+            // Check that contract has been bought.
+            let buyer = match self.buyer.get(id){
+                Some(p) => p,
+                None => { return Err(Error::ContractNotPurchased) }
+            };
+            // Check that inspector is caller
+            let caller = self.env().caller();
+            if self.inspector.get(id) != Some(caller) || !self.has_role(Role::Inspector, caller) {
+                return Err(Error::OnlyInspectorCanAttest)
+            }
+            if self.attestation.get(id).is_some() {
+                return Err(Error::AlreadyAttested)
+            }
+            if quality_reading.is_some() && self.quality_spec.get(id).is_none() {
+                return Err(Error::QualitySpecNotFound)
+            }
+
+            let attestation = Attestation {
+                inspector: caller,
+                delivered_grams,
+                quality_reading,
+                report_hash,
+                block: self.env().block_number(),
+            };
+            self.attestation.insert(id, &attestation);
+
+            Self::env().emit_event(DeliveryAttested {
+                contract_id: id,
+                inspector: caller,
+                delivered_grams,
+                report_hash,
+            });
+
+            // Settle automatically once the finality block has passed, unless
+            // settlement is paused
+            if self.finality_block.get(id).unwrap() < self.env().block_number()
+                && !self.is_paused(PauseScope::Settlement)
+            {
+                let settlement = Settlement {
+                    seller,
+                    buyer,
+                    reading: quality_reading,
+                    delivered_grams: Some(delivered_grams),
+                };
+                self.settle(id, settlement)?;
+            }
+
+            Ok(())
+        }
+
+        /// Finalize the contract: transfer total from the contract account to the seller.
+        /// 
+        /// The buyer calls this function when they have received their product.
+        /// 
+        /// Only the buyer can call this function, unless the contract names an
+        /// inspector. Inspected contracts settle according to the inspector's
+        /// attestation and can be finalized by anyone once it has been submitted.
+        /// If the inspector has not attested by `ATTESTATION_WINDOW` blocks after
+        /// the finality block, the buyer can finalize on its own word instead, and
+        /// the inspector forgoes its fee.
+        /// 
+        /// This function can only be called at or after the finality block.
+        /// 
+        /// # Errors
+        /// 
+        /// Returns Paused if settlement is paused.
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        /// 
+        /// Returns CannotFinalizeBeforeFinalityBlock if the caller attempts to 
+        /// finalize the contract before the finality block.
+        /// 
+        /// Returns OnlyBuyerCanFinalize if a caller other than the buyer for the 
+        /// given contract attempts to finalize.
+        ///
+        /// Returns AttestationNotFound if the contract names an inspector who has
+        /// not attested delivery yet, unless the buyer finalizes after the
+        /// attestation deadline.
+        /// 
+        /// In theory, would return InsufficientBalance if the contract account 
+        /// does not have enough funds to pay the seller, but this should never
+        /// occur.
+        ///
+        /// Returns TokenTransferFailed if the PSP22 token of the contract rejects
+        /// a payout.
+        ///
+        /// Returns NativeTransferFailed if the contract account cannot pay out a
+        /// native escrow without dropping below the existential deposit.
+        #[ink(message)]
+        pub fn finalize(&mut self, id: ContractId) -> Result<(), Error> {
+            self.finalize_impl(id, None)
+        }
+
+        /// Finalize a graded contract using the quality reading of the delivered lot.
+        ///
+        /// The reading must be reported by a holder of the Oracle role who is neither
+        /// the buyer nor the seller, so that neither party grades its own lot.
+        ///
+        /// The reading is graded against the contract's quality spec and schedule. The
+        /// seller receives `total` minus the resulting discount and the difference is
+        /// refunded to the buyer. The reading is stored for later queries.
+        ///
+        /// # Errors
+        ///
+        /// Returns QualitySpecNotFound if the contract has no quality spec.
+        ///
+        /// Returns OnlyInspectorCanGrade if the contract names an inspector.
+        ///
+        /// Returns OnlyOracleCanGrade if the caller lacks the Oracle role or is the
+        /// buyer or seller of the contract.
+        ///
+        /// Otherwise returns the same errors as `finalize`, except that the caller
+        /// need not be the buyer.
+        #[ink(message)]
+        pub fn finalize_with_quality(
+            &mut self,
+            id: ContractId,
+            reading: QualityReading,
+        ) -> Result<(), Error> {
+            self.finalize_impl(id, Some(reading))
+        }
+
+        /// Finalizes many contracts at once.
+        ///
+        /// In `Atomic` mode every contract is checked up front and either all
+        /// contracts settle or none do.
+        ///
+        /// In `BestEffort` mode each contract is finalized on its own and the result
+        /// of every settlement is returned in order.
+        ///
+        /// # Errors
+        ///
+        /// Returns BatchTooLarge if more than `MAX_BATCH_SIZE` ids are given.
+        ///
+        /// In `Atomic` mode, returns ContractAlreadyFinalized if an id is repeated,
+        /// and otherwise the first error `finalize` would return for any of the ids.
+        #[ink(message)]
+        pub fn finalize_many(
+            &mut self,
+            ids: Vec<ContractId>,
+            mode: BatchMode,
+        ) -> Result<Vec<Result<(), Error>>, Error> {
+            if ids.len() > MAX_BATCH_SIZE {
+                return Err(Error::BatchTooLarge)
+            }
+            if mode == BatchMode::BestEffort {
+                return Ok(ids.iter().map(|id| self.finalize(*id)).collect())
+            }
+
+            // Check every contract up front
+            let mut settlements = Vec::with_capacity(ids.len());
+            for (i, id) in ids.iter().enumerate() {
+                if ids[..i].contains(id) {
+                    return Err(Error::ContractAlreadyFinalized)
+                }
+                settlements.push(self.check_finalize(*id, None)?);
+            }
+
+            ids.iter()
+                .zip(settlements)
+                .map(|(id, settlement)| self.settle(*id, settlement).map(Ok))
+                .collect()
+        }
+
+        /// Settles a disputed or stuck contract on the ruling of an arbiter.
+        ///
+        /// The ruling replaces the buyer's word and any attestation: deliveries
+        /// short of the listed weight are paid pro rata and the quality reading, if
+        /// any, is graded against the contract's spec. The inspector fee is only
+        /// paid if the inspector attested delivery.
+        ///
+        /// The caller must hold the Arbiter role and be neither the buyer, the
+        /// seller nor the inspector of the contract.
+        ///
+        /// # Errors
+        ///
+        /// Returns Paused if settlement is paused.
+        ///
+        /// Returns OnlyArbiterCanResolve if the caller lacks the Arbiter role or is
+        /// a party to the contract.
+        ///
+        /// Returns QualitySpecNotFound if a reading is given for a contract without a spec.
+        ///
+        /// Otherwise returns the same errors as `finalize`, except that the caller
+        /// need not be the buyer and no attestation is needed.
+        #[ink(message)]
+        pub fn arbitrate(
+            &mut self,
+            id: ContractId,
+            delivered_grams: Grams,
+            quality_reading: Option<QualityReading>,
+        ) -> Result<(), Error> {
+            self.ensure_unpaused(PauseScope::Settlement)?;
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that it has not already been finalized
+            if self.is_finalized(id).unwrap() {
+                return Err(Error::ContractAlreadyFinalized);
+            }
+            // Check that current block >= to the finality block of the contract
+            if self.finality_block.get(id).unwrap() >= self.env().block_number() {
+                return Err(Error::CannotFinalizeBeforeFinalityBlock)
+            }
+            // Check that contract has been bought.
+            let buyer = match self.buyer.get(id){
+                Some(p) => p,
+                None => { return Err(Error::ContractNotPurchased) }
+            };
+            // Rulings come from a neutral arbiter, never from a party to the contract
+            let caller = self.env().caller();
+            if caller == buyer
+                || caller == seller
+                || self.inspector.get(id) == Some(caller)
+                || !self.has_role(Role::Arbiter, caller)
+            {
+                return Err(Error::OnlyArbiterCanResolve)
+            }
+            if quality_reading.is_some() && self.quality_spec.get(id).is_none() {
+                return Err(Error::QualitySpecNotFound)
+            }
+
+            let settlement = Settlement {
+                seller,
+                buyer,
+                reading: quality_reading,
+                delivered_grams: Some(delivered_grams),
+            };
+            self.settle(id, settlement)?;
+
+            Self::env().emit_event(DisputeResolved {
+                contract_id: id,
+                arbiter: caller,
+                delivered_grams,
+            });
+
+            Ok(())
+        }
+
+        /// Returns the escrow of a bought contract to its buyer while settlement is
+        /// paused, so funds are never trapped behind a pause.
+        ///
+        /// Only contracts still at or before their finality block can be refunded,
+        /// and only if no inspector attested delivery. Past the finality block the
+        /// seller may have delivered, so the escrow stays in place for `finalize`
+        /// or `arbitrate` once settlement resumes, and attested contracts settle on
+        /// the attestation.
+        ///
+        /// The contract is settled as if nothing was delivered: the seller keeps the
+        /// price but receives none of `total`, and no inspector fee is paid since
+        /// the inspector did not attest.
+        ///
+        /// # Errors
+        ///
+        /// Returns NotPaused if settlement is not paused.
+        ///
+        /// Returns ContractNotFound if there is no seller for the given contract.
+        ///
+        /// Returns ContractAlreadyFinalized if the contract has already been finalized.
+        ///
+        /// Returns ContractNotPurchased if the contract has not been bought.
+        ///
+        /// Returns OnlyBuyerCanFinalize if a caller other than the buyer requests the refund.
+        ///
+        /// Returns FinalityBlockPassed if the finality block has passed.
+        ///
+        /// Returns AlreadyAttested if the inspector already attested delivery.
+        #[ink(message)]
+        pub fn refund_escrow(&mut self, id: ContractId) -> Result<(), Error> {
+            if !self.is_paused(PauseScope::Settlement) {
+                return Err(Error::NotPaused)
+            }
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that it has not already been finalized
+            if self.is_finalized(id).unwrap() {
+                return Err(Error::ContractAlreadyFinalized);
+            }
+            // Check that contract has been bought.
+            let buyer = match self.buyer.get(id){
+                Some(p) => p,
+                None => { return Err(Error::ContractNotPurchased) }
+            };
+            // Check that buyer is caller
+            if self.env().caller() != buyer {
+                return Err(Error::OnlyBuyerCanFinalize)
+            }
+            // Check that the seller was not due to deliver yet
+            if self.finality_block.get(id).unwrap() < self.env().block_number() {
+                return Err(Error::FinalityBlockPassed)
+            }
+            // Attested deliveries settle on the attestation once unpaused
+            if self.attestation.contains(id) {
+                return Err(Error::AlreadyAttested)
+            }
+
+            let settlement = Settlement {
+                seller,
+                buyer,
+                reading: None,
+                delivered_grams: Some(0),
+            };
+            self.settle(id, settlement)
+        }
+
+        /// Sends the caller the native currency held for it because the runtime
+        /// rejected a payout, and returns the amount.
+        ///
+        /// # Errors
+        ///
+        /// Returns NativeTransferFailed if the runtime rejects the transfer, e.g.
+        /// because the amount is below the existential deposit of an account that
+        /// does not exist yet. `claim_native_to` can send it elsewhere instead.
+        #[ink(message)]
+        pub fn claim_native(&mut self) -> Result<Balance, Error> {
+            self.claim_native_to(self.env().caller())
+        }
+
+        /// Sends the native currency held for the caller to `to`, and returns the
+        /// amount.
+        ///
+        /// Lets a recipient whose account cannot receive a payout below the
+        /// existential deposit collect it on an account that already exists.
+        ///
+        /// # Errors
+        ///
+        /// Returns NativeTransferFailed if the runtime rejects the transfer.
+        ///
+        /// Returns Overflow if the sum held for every account is smaller than the
+        /// amount, which only a corrupt ledger allows.
+        #[ink(message)]
+        pub fn claim_native_to(&mut self, to: AccountId) -> Result<Balance, Error> {
+            let caller = self.env().caller();
+            let value = self.unclaimed_native_of(caller);
+            if value == 0 {
+                return Ok(0)
+            }
+            let spendable = self.env().balance().saturating_sub(self.env().minimum_balance());
+            if value > spendable || self.env().transfer(to, value).is_err() {
+                return Err(Error::NativeTransferFailed)
+            }
+            self.release_unclaimed(caller, to, value)?;
+            Ok(value)
+        }
+
+        /// Shared settlement logic of `finalize` and `finalize_with_quality`.
+        fn finalize_impl(
+            &mut self,
+            id: ContractId,
+            reading: Option<QualityReading>,
+        ) -> Result<(), Error> {
+            let settlement = self.check_finalize(id, reading)?;
+            self.settle(id, settlement)
+        }
+
+        /// Checks that a contract can be finalized by the caller with the given reading.
+        ///
+        /// # Errors
+        ///
+        /// Returns the errors documented on `finalize` and `finalize_with_quality`.
+        fn check_finalize(
+            &self,
+            id: ContractId,
+            reading: Option<QualityReading>,
+        ) -> Result<Settlement, Error> {
+            self.ensure_unpaused(PauseScope::Settlement)?;
+            // Check that contract exists.
+            let seller = match self.seller.get(id) {
+                Some(p) => p,
+                None => return Err(Error::ContractNotFound)
+            };
+            // Check that it has not already been finalized
+            if self.is_finalized(id).unwrap() {
+                return Err(Error::ContractAlreadyFinalized);
+            }
+            // Check that current block >= to the finality block of the contract
+            if self.finality_block.get(id).unwrap() >= self.env().block_number() {
+                return Err(Error::CannotFinalizeBeforeFinalityBlock)
+            }
+            // Check that contract has been bought.
+            let buyer = match self.buyer.get(id){
+                Some(p) => p,
+                None => { return Err(Error::ContractNotPurchased) }
+            };
+
+            // Inspected contracts settle on the inspector's word
+            if self.inspector.get(id).is_some() {
+                if reading.is_some() {
+                    return Err(Error::OnlyInspectorCanGrade)
+                }
+                let attestation = match self.attestation.get(id) {
+                    Some(p) => p,
+                    // The buyer's word stands in for an inspector who never attested
+                    None if self.env().caller() == buyer
+                        && self.get_attestation_deadline(id).unwrap() < self.env().block_number() =>
+                    {
+                        return Ok(Settlement {
+                            seller,
+                            buyer,
+                            reading: None,
+                            delivered_grams: None,
+                        })
+                    }
+                    None => return Err(Error::AttestationNotFound)
+                };
+                return Ok(Settlement {
+                    seller,
+                    buyer,
+                    reading: attestation.quality_reading,
+                    delivered_grams: Some(attestation.delivered_grams),
+                })
+            }
+
+            if reading.is_some() {
+                // Readings come from a neutral oracle, never from either party
+                let caller = self.env().caller();
+                if caller == buyer
+                    || caller == seller
+                    || !self.has_role(Role::Oracle, caller)
+                {
+                    return Err(Error::OnlyOracleCanGrade)
+                }
+                if self.quality_spec.get(id).is_none() {
+                    return Err(Error::QualitySpecNotFound)
+                }
+            } else if self.env().caller() != buyer {
+                // Check that buyer is caller
+                return Err(Error::OnlyBuyerCanFinalize)
+            }
+            Ok(Settlement {
+                seller,
+                buyer,
+                reading,
+                delivered_grams: None,
+            })
+        }
+
+        /// Releases the escrow of a contract between seller, buyer and inspector.
+        ///
+        /// Deliveries short of the listed weight are paid pro rata, the quality
+        /// discount is applied to what was delivered and the settlement fee and the
+        /// inspector fee come out of the seller's share. The inspector fee is only
+        /// paid once the inspector attested delivery. Whatever the seller is not
+        /// owed is refunded to the buyer.
+        fn settle(&mut self, id: ContractId, settlement: Settlement) -> Result<(), Error> {
+            let Settlement {
+                seller,
+                buyer,
+                reading,
+                delivered_grams,
+            } = settlement;
+            // Release the buyer's `total` from the escrow ledger
+            let asset = self.settlement_asset_of(id);
+            let total = self.release_escrow(id)?;
+            let pooled = self.pooled(id, total);
+            let weight = self.weight.get(id).unwrap();
+
+            // Pay short deliveries pro rata against the listed weight
+            let delivered = match delivered_grams {
+                Some(0) => 0,
+                Some(grams) if grams < weight => {
+                    proportion(total, Balance::from(grams), Balance::from(weight))
+                }
+                _ => total,
+            };
+            // Grade the delivered lot against the spec
+            let discount = match reading {
+                Some(reading) => {
+                    let spec = self.quality_spec.get(id).unwrap();
+                    let schedule = self.quality_schedule.get(id).unwrap_or_default();
+                    self.quality_reading.insert(id, &reading);
+                    schedule.discount(&spec, &reading)
+                }
+                None => 0,
+            };
+            let owed = delivered
+                - proportion(delivered, Balance::from(discount), Balance::from(MAX_BASIS_POINTS));
+            let refund = total - owed;
+            let (protocol_fee, treasury) = self.protocol_fee(owed, |fees| fees.settlement_fee);
+            let fee = if self.attestation.contains(id) {
+                self.inspector_fee.get(id).unwrap_or_default().min(owed - protocol_fee)
+            } else {
+                0
+            };
+            let payout = owed - protocol_fee - fee;
+
+            // Pay out the escrow from the contract account
+            let pool = self.env().account_id();
+            if payout > 0 {
+                self.pay(asset, &pool, &seller, payout)?;
+            }
+            if refund > 0 {
+                self.pay(asset, &pool, &buyer, refund)?;
+            }
+            if fee > 0 {
+                let inspector = self.inspector.get(id).unwrap();
+                self.pay(asset, &pool, &inspector, fee)?;
+            }
+            if let Some(treasury) = treasury.filter(|_| protocol_fee > 0) {
+                self.pay(asset, &pool, &treasury, protocol_fee)?;
+            }
+
+            self.finalized.insert(id, &true);
+            self.closed_at.insert(id, &self.env().block_number());
+            self.move_status(id, Some(ContractStatus::Bought), ContractStatus::Finalized)?;
+            self.update_exposure(seller, |exposure| {
+                exposure.receivable = exposure.receivable.checked_sub(pooled)?;
+                Some(())
+            })?;
+            self.update_exposure(buyer, |exposure| {
+                exposure.open_positions = exposure.open_positions.checked_sub(1)?;
+                exposure.locked = exposure.locked.checked_sub(pooled)?;
+                Some(())
+            })?;
+
+            Self::env().emit_event(ContractFinalized {
+                contract_id: id,
+                buyer,
+                total,
+                payout,
+                refund,
+                inspector_fee: fee,
+                protocol_fee,
+            });
+
+            Ok(())
+        }
+
+        /// Pays `value` of a settlement asset from `from` to `to`.
+        ///
+        /// Internal tokens move between balances directly. PSP22 tokens leave the
+        /// contract account with a cross-contract `transfer`, and any other account
+        /// through `transfer_from`, spending the allowance it gave this contract
+        /// on the token. The native currency is only ever paid out of the contract
+        /// account, with `pay_native`.
+        ///
+        /// # Errors
+        ///
+        /// Returns InsufficientBalance or Overflow if an internal transfer fails.
+        ///
+        /// Returns TokenTransferFailed if the call to the PSP22 token fails or the
+        /// token rejects the transfer.
+        ///
+        /// Returns NativeTransferFailed as documented on `pay_native`.
+        fn pay(
+            &mut self,
+            asset: SettlementAsset,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<(), Error> {
+            let token = match asset {
+                SettlementAsset::Internal => return self.transfer_from_to(from, to, value),
+                SettlementAsset::Native => return self.pay_native(to, value),
+                SettlementAsset::Psp22(token) => token,
+            };
+            if value == 0 {
+                return Ok(())
+            }
+            let call = build_call::<ink::env::DefaultEnvironment>().call(token);
+            let result = if *from == self.env().account_id() {
+                call.exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER))
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<Result<(), PSP22Error>>()
+                .try_invoke()
+            } else {
+                call.exec_input(
+                    ExecutionInput::new(Selector::new(PSP22_TRANSFER_FROM))
+                        .push_arg(from)
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<Result<(), PSP22Error>>()
+                .try_invoke()
+            };
+            match result {
+                Ok(Ok(Ok(()))) => Ok(()),
+                _ => Err(Error::TokenTransferFailed),
+            }
+        }
+
+        /// Sends `value` of the native currency from the contract account to `to`.
+        ///
+        /// Anything already held for `to` is sent along with it, so amounts too
+        /// small to reach a new account on their own arrive with a later payout.
+        /// The runtime rejects transfers that leave the recipient below the
+        /// existential deposit. Payouts it rejects are held for `to` to claim with
+        /// `claim_native` or `claim_native_to` instead of failing the settlement.
+        ///
+        /// # Errors
+        ///
+        /// Returns NativeTransferFailed if the contract account cannot spend `value`
+        /// without dropping below the existential deposit itself.
+        fn pay_native(&mut self, to: &AccountId, value: Balance) -> Result<(), Error> {
+            if value == 0 {
+                return Ok(())
+            }
+            // Keep the contract account itself above the existential deposit
+            let spendable = self.env().balance().saturating_sub(self.env().minimum_balance());
+            if value > spendable {
+                return Err(Error::NativeTransferFailed)
+            }
+            // Combine the payout with anything held for `to`
+            let pending = self.unclaimed_native_of(*to);
+            if pending > 0 {
+                let combined = value.checked_add(pending).ok_or(Error::Overflow)?;
+                if combined <= spendable && self.env().transfer(*to, combined).is_ok() {
+                    return self.release_unclaimed(*to, *to, pending)
+                }
+            }
+            if self.env().transfer(*to, value).is_ok() {
+                return Ok(())
+            }
+            self.defer_native(to, value)
+        }
+
+        /// Holds `value` of the native currency for `to` to claim later.
+        ///
+        /// # Errors
+        ///
+        /// Returns Overflow if the amount held would overflow.
+        fn defer_native(&mut self, to: &AccountId, value: Balance) -> Result<(), Error> {
+            let unclaimed = self.unclaimed_native_of(*to).checked_add(value).ok_or(Error::Overflow)?;
+            let total_unclaimed = self.total_unclaimed_native().checked_add(value).ok_or(Error::Overflow)?;
+            self.unclaimed_native.insert(to, &unclaimed);
+            self.total_unclaimed_native.set(&total_unclaimed);
+            self.env().emit_event(PayoutDeferred {
+                account: *to,
+                value,
+            });
+            Ok(())
+        }
+
+        /// Clears the `value` held for `account` once it was sent to `to`.
+        ///
+        /// # Errors
+        ///
+        /// Returns Overflow if the sum held for every account is smaller than
+        /// `value`, which only a corrupt ledger allows.
+        fn release_unclaimed(
+            &mut self,
+            account: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), Error> {
+            let total_unclaimed = self.total_unclaimed_native().checked_sub(value).ok_or(Error::Overflow)?;
+            self.unclaimed_native.remove(account);
+            self.total_unclaimed_native.set(&total_unclaimed);
+            self.env().emit_event(PayoutClaimed { account, to, value });
+            Ok(())
+        }
+    }
+
+    /// Hooks letting unit tests put the contract in states its messages never produce.
+    #[cfg(test)]
+    impl SsalCommods {
+        /// Overwrites the balance of `account` without touching `total_supply`.
+        fn corrupt_balance(&mut self, account: AccountId, balance: Balance) {
+            self.balances.insert(account, &balance);
+            self.track_holder(&account).unwrap();
+        }
+
+        /// Overwrites the escrow ledger entry of `id`.
+        fn corrupt_escrow(&mut self, id: ContractId, escrow: Balance) {
+            self.escrow.insert(id, &escrow);
+        }
+
+        /// Overwrites the number of contracts in the status index of `status`.
+        fn corrupt_status_count(&mut self, status: ContractStatus, count: u64) {
+            self.status_contract_count.insert(status, &count);
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    /// The below code is technically just normal Rust code.
+    #[cfg(test)]
+    mod tests {
+        /// Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        const RICE: SeriesId = 0;
+
+        /// Storage layout of the original code, which predates versioning. The
+        /// struct and its fields keep their original names, so entries land under
+        /// the same storage keys as they did on chain.
+        mod baseline {
+            use super::*;
+
+            #[ink::storage_item]
+            #[derive(Default)]
+            pub struct SsalCommods {
+                pub seller: Mapping<ContractId, AccountId>,
+                pub buyer: Mapping<ContractId, AccountId>,
+                pub price: Mapping<ContractId, Balance>,
+                pub total: Mapping<ContractId, Balance>,
+                pub weight: Mapping<ContractId, Grams>,
+                pub finality_block: Mapping<ContractId, BlockNumber>,
+                pub finalized: Mapping<ContractId, bool>,
+                pub contract_count: ContractId,
+                pub total_supply: Balance,
+                pub balances: Mapping<AccountId, Balance>,
+                pub allowances: Mapping<(AccountId, AccountId), Balance>,
+            }
+
+            impl SsalCommods {
+                /// Moves tokens the way the original `transfer` did.
+                pub fn transfer(&mut self, from: AccountId, to: AccountId, value: Balance) {
+                    let from_balance = self.balances.get(from).unwrap_or_default();
+                    let to_balance = self.balances.get(to).unwrap_or_default();
+                    self.balances.insert(from, &(from_balance - value));
+                    self.balances.insert(to, &(to_balance + value));
+                }
+
+                /// Lists a free contract the way the original `create_contract` did.
+                pub fn list(&mut self, seller: AccountId, total: Balance) -> ContractId {
+                    let id = self.contract_count;
+                    self.seller.insert(id, &seller);
+                    self.price.insert(id, &0);
+                    self.total.insert(id, &total);
+                    self.weight.insert(id, &1_000);
+                    self.finality_block.insert(id, &1);
+                    self.finalized.insert(id, &false);
+                    self.contract_count = id + 1;
+                    id
+                }
+
+                /// Buys a contract the way the original `buy_contract` did, moving
+                /// `total` to the contract account.
+                pub fn buy(&mut self, id: ContractId, buyer: AccountId, pool: AccountId) {
+                    self.buyer.insert(id, &buyer);
+                    self.transfer(buyer, pool, self.total.get(id).unwrap());
+                }
+
+                /// Settles a contract the way the original `finalize` did.
+                pub fn finalize(&mut self, id: ContractId, pool: AccountId) {
+                    self.transfer(pool, self.seller.get(id).unwrap(), self.total.get(id).unwrap());
+                    self.finalized.insert(id, &true);
+                }
+            }
+        }
+
+        /// Deploys the contract with a single rice series in the registry.
+        fn new_market(total_supply: Balance) -> SsalCommods {
+            let mut ssal = SsalCommods::new(total_supply);
+            assert_eq!(
+                ssal.register_series(
+                    String::from("Japonica"),
+                    1,
+                    String::from("Busan"),
+                    Unit::Tonnes,
+                ),
+                Ok(RICE)
+            );
+            ssal
+        }
+
+        // COMMOD TESTS
+
+        /// We test if the default constructor does its job.
+        #[ink::test]
+        fn default_works() {
+            let ssal = SsalCommods::new(100_000);
+            assert_eq!(ssal.get_contract_count(), 0);
+            assert_eq!(ssal.total_supply(), 100_000);
+        }
+
+        /// We test the PSP22 metadata and allowance messages.
+        #[ink::test]
+        fn psp22_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let plain = SsalCommods::new(100_000);
+            assert_eq!(plain.token_name(), None);
+            assert_eq!(plain.token_decimals(), 0);
+
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let mut ssal = SsalCommods::new_with_metadata(
+                100_000,
+                Some(String::from("Ssal")),
+                Some(String::from("SSAL")),
+                2,
+            );
+            assert_eq!(ssal.token_name(), Some(String::from("Ssal")));
+            assert_eq!(ssal.token_symbol(), Some(String::from("SSAL")));
+            assert_eq!(ssal.token_decimals(), 2);
+
+            assert_eq!(ssal.approve(accounts.bob, 100), Ok(()));
+            assert_eq!(ssal.increase_allowance(accounts.bob, 50), Ok(()));
+            assert_eq!(ssal.decrease_allowance(accounts.bob, 30), Ok(()));
+            assert_eq!(ssal.allowance(accounts.alice, accounts.bob), 120);
+
+            // Bob spends part of the allowance on Charlie.
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                ssal.transfer_from(accounts.alice, accounts.charlie, 20, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(ssal.allowance(accounts.alice, accounts.bob), 100);
+            assert_eq!(ssal.balance_of(accounts.charlie), 20);
+
+            // Zero-value and self transfers are no-ops.
+            assert_eq!(ssal.transfer(accounts.charlie, 0, Vec::new()), Ok(()));
+            assert_eq!(ssal.transfer(accounts.bob, 1, Vec::new()), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn psp22_fails() {
+            let mut ssal = SsalCommods::new(100_000);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(ssal.approve(accounts.bob, 10), Ok(()));
+            assert_eq!(
+                ssal.decrease_allowance(accounts.bob, 11),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                ssal.increase_allowance(accounts.bob, Balance::MAX),
+                Err(PSP22Error::Custom(String::from("Overflow")))
+            );
+            assert_eq!(ssal.allowance(accounts.alice, accounts.bob), 10);
+            assert_eq!(
+                ssal.transfer(accounts.bob, 100_001, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                ssal.transfer_from(accounts.alice, accounts.charlie, 11, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+        }
+
+        /// We test a simple use case of our contract.
+        #[ink::test]
+        fn create_contract_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice owns all the tokens on contract instantiation
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
+            // Bob does not owns tokens
+            assert_eq!(ssal.balance_of(accounts.bob), 0);
+
+            // Test correct input.
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            assert_eq!(ssal.get_contract_count(), 1);
+            assert_eq!(ssal.get_seller(0), Some(accounts.alice));
+            assert_eq!(ssal.get_buyer(0), None);
+            assert_eq!(ssal.get_finality_block(0), Some(20));
+            assert_eq!(ssal.get_price(0), Some(10));
+            assert_eq!(ssal.get_total(0), Some(10_000));
+            assert_eq!(ssal.get_weight(0), Some(10));
+        }
+
+        // Test possible failiures for create_contract
+        #[ink::test]
+        fn create_contract_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice owns all the tokens on contract instantiation
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
+            // Bob does not owns tokens
+            assert_eq!(ssal.balance_of(accounts.bob), 0);
+
+            // Test correct input.
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            assert_eq!(ssal.get_contract_count(), 1);
+
+            // Test faulty input.
+            // Advance block number by 5
+            for _ in 1..5{
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(
+                ssal.create_contract(RICE, 10, 10_000, 10, 1), 
+                Err(Error::InvalidBlockNumber)
+            );
+            assert_eq!(ssal.get_contract_count(), 1);
+        }
+
+        fn rice_listing(finality_block: BlockNumber) -> ListingParams {
+            ListingParams {
+                series: RICE,
+                price: 10,
+                total: 10_000,
+                weight: 10,
+                finality_block,
+            }
+        }
+
+        #[ink::test]
+        fn create_contracts_works() {
+            let mut ssal = new_market(100_000);
+
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            let listings = vec![rice_listing(20), rice_listing(30), rice_listing(40)];
+            assert_eq!(ssal.create_contracts(listings), Ok(vec![1, 2, 3]));
+            assert_eq!(ssal.get_contract_count(), 4);
+            assert_eq!(ssal.get_finality_block(3), Some(40));
+            assert_eq!(ssal.contracts_by_series(RICE, 0, 10), vec![0, 1, 2, 3]);
+            assert_eq!(ssal.create_contracts(Vec::new()), Ok(Vec::new()));
+        }
+
+        #[ink::test]
+        fn create_contracts_fails() {
+            let mut ssal = new_market(100_000);
+
+            // A single invalid listing rejects the whole batch
+            let listings = vec![rice_listing(20), rice_listing(20), ListingParams {
+                series: 1,
+                ..rice_listing(20)
+            }];
+            assert_eq!(ssal.create_contracts(listings), Err(Error::SeriesNotFound));
+            assert_eq!(ssal.get_contract_count(), 0);
+            assert_eq!(ssal.get_seller(0), None);
+
+            let listings = vec![rice_listing(20); MAX_BATCH_SIZE + 1];
+            assert_eq!(ssal.create_contracts(listings), Err(Error::BatchTooLarge));
+
+            // A series index running out of room midway rejects the batch up front
+            ssal.series_contract_count.insert(RICE, &(u64::MAX - 1));
+            let listings = vec![rice_listing(20), rice_listing(20)];
+            assert_eq!(ssal.create_contracts(listings), Err(Error::Overflow));
+            assert_eq!(ssal.get_contract_count(), 0);
+            assert_eq!(ssal.get_seller(0), None);
+            ssal.series_contract_count.remove(RICE);
+
+            ssal.contract_count = ContractId::MAX - 1;
+            let listings = vec![rice_listing(20), rice_listing(20)];
+            assert_eq!(ssal.create_contracts(listings), Err(Error::Overflow));
+            assert_eq!(ssal.get_seller(ContractId::MAX - 1), None);
+        }
+
+        #[ink::test]
+        fn register_series_works() {
+            let mut ssal = new_market(100_000);
+
+            let indica = ssal.register_series(
+                String::from("Indica"),
+                2,
+                String::from("Incheon"),
+                Unit::Sack(80_000),
+            );
+            assert_eq!(indica, Ok(1));
+            assert_eq!(ssal.get_series_count(), 2);
+            assert_eq!(ssal.get_series(1).unwrap().commodity, String::from("Indica"));
+
+            // Listings are filtered by the series they reference
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            assert_eq!(ssal.create_contract(1, 10, 10_000, 10, 20), Ok(1));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(2));
+            assert_eq!(ssal.get_contract_series(1), Some(1));
+            assert_eq!(ssal.contracts_by_series(RICE, 0, 10), vec![0, 2]);
+            assert_eq!(ssal.contracts_by_series(RICE, 1, 10), vec![2]);
+            assert_eq!(ssal.contracts_by_series(RICE, 0, 1), vec![0]);
+            assert_eq!(ssal.contracts_by_series(1, 0, 10), vec![1]);
+            assert_eq!(ssal.contracts_by_series(2, 0, 10), Vec::<ContractId>::new());
+        }
+
+        #[ink::test]
+        fn register_series_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Listings must reference a registered series
+            assert_eq!(
+                ssal.create_contract(1, 10, 10_000, 10, 20),
+                Err(Error::SeriesNotFound)
+            );
+            assert_eq!(ssal.retire_series(1), Err(Error::SeriesNotFound));
+
+            // Only the owner manages the registry
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                ssal.register_series(
+                    String::from("Indica"),
+                    2,
+                    String::from("Incheon"),
+                    Unit::Sack(80_000),
+                ),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(ssal.retire_series(RICE), Err(Error::NotOwner));
+
+            // Retired series stop accepting listings
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.retire_series(RICE), Ok(()));
+            assert_eq!(
+                ssal.create_contract(RICE, 10, 10_000, 10, 20),
+                Err(Error::SeriesRetired)
+            );
+            assert_eq!(ssal.get_contract_count(), 0);
+        }
+
+        #[ink::test]
+        fn transfer_ownership_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.get_owner(), Some(accounts.alice));
+
+            // Alice stays the owner until Bob accepts
+            assert_eq!(ssal.transfer_ownership(accounts.bob), Ok(()));
+            assert_eq!(ssal.get_owner(), Some(accounts.alice));
+            assert_eq!(ssal.get_pending_owner(), Some(accounts.bob));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.accept_ownership(), Ok(()));
+            assert_eq!(ssal.get_owner(), Some(accounts.bob));
+            assert_eq!(ssal.get_pending_owner(), None);
+            assert_eq!(ssal.retire_series(RICE), Ok(()));
+
+            // Renouncing leaves the contract without an administrator
+            assert_eq!(ssal.renounce_ownership(), Ok(()));
+            assert_eq!(ssal.get_owner(), None);
+        }
+
+        #[ink::test]
+        fn transfer_ownership_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Only the pending owner can accept
+            assert_eq!(ssal.transfer_ownership(accounts.bob), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.accept_ownership(), Err(Error::NotPendingOwner));
+            assert_eq!(ssal.transfer_ownership(accounts.charlie), Err(Error::NotOwner));
+            assert_eq!(ssal.renounce_ownership(), Err(Error::NotOwner));
+
+            // A new nomination replaces the pending one
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.transfer_ownership(accounts.charlie), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.accept_ownership(), Err(Error::NotPendingOwner));
+
+            // Renouncing drops the pending nomination
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.renounce_ownership(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.accept_ownership(), Err(Error::NotPendingOwner));
+            assert_eq!(ssal.get_owner(), None);
+        }
+
+        #[ink::test]
+        fn former_owner_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Admin moves from Alice to Bob along with ownership
+            assert_eq!(ssal.transfer_ownership(accounts.bob), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.accept_ownership(), Ok(()));
+            assert!(!ssal.has_role(Role::Admin, accounts.alice));
+            assert!(ssal.has_role(Role::Admin, accounts.bob));
+
+            // Alice can no longer mint, pause or hand out roles
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.mint(accounts.alice, 1), Err(Error::NotOwner));
+            assert_eq!(ssal.pause(PauseScope::Listing), Err(Error::MissingRole));
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.alice), Err(Error::MissingRole));
+
+            // Once Bob renounces, nobody administers the contract
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.renounce_ownership(), Ok(()));
+            assert!(!ssal.has_role(Role::Admin, accounts.bob));
+            assert_eq!(ssal.mint(accounts.bob, 1), Err(Error::NotOwner));
+            assert_eq!(ssal.pause(PauseScope::Listing), Err(Error::MissingRole));
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.bob), Err(Error::MissingRole));
+            assert_eq!(ssal.total_supply(), 100_000);
+            assert!(!ssal.is_paused(PauseScope::Listing));
+            assert!(!ssal.has_role(Role::Arbiter, accounts.alice));
+            assert!(!ssal.has_role(Role::Arbiter, accounts.bob));
+        }
+
+        #[ink::test]
+        fn grant_role_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert!(ssal.has_role(Role::Admin, accounts.alice));
+
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.bob), Ok(()));
+            assert!(ssal.has_role(Role::Arbiter, accounts.bob));
+            assert_eq!(ssal.revoke_role(Role::Arbiter, accounts.bob), Ok(()));
+            assert!(!ssal.has_role(Role::Arbiter, accounts.bob));
+
+            // Arbiters administer inspectors once the owner says so
+            assert_eq!(ssal.set_role_admin(Role::Inspector, Role::Arbiter), Ok(()));
+            assert_eq!(ssal.get_role_admin(Role::Inspector), Role::Arbiter);
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.bob), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+            assert!(ssal.has_role(Role::Inspector, accounts.charlie));
+
+            // Members can give up their own roles
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.renounce_role(Role::Inspector), Ok(()));
+            assert!(!ssal.has_role(Role::Inspector, accounts.charlie));
+
+            // Only verified sellers list once the policy requires it
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.set_seller_policy(true), Ok(()));
+            assert!(ssal.requires_verified_seller());
+            assert_eq!(ssal.grant_role(Role::VerifiedSeller, accounts.alice), Ok(()));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+        }
+
+        #[ink::test]
+        fn grant_role_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Only members of the admin role manage a role
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.grant_role(Role::Admin, accounts.bob), Err(Error::MissingRole));
+            assert_eq!(ssal.revoke_role(Role::Admin, accounts.alice), Err(Error::MissingRole));
+            assert_eq!(ssal.set_role_admin(Role::Pauser, Role::Pauser), Err(Error::NotOwner));
+            assert_eq!(ssal.set_seller_policy(true), Err(Error::NotOwner));
+
+            // Admin is held by the owner alone
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.grant_role(Role::Admin, accounts.bob), Err(Error::AdminRoleReserved));
+            assert_eq!(ssal.revoke_role(Role::Admin, accounts.alice), Err(Error::AdminRoleReserved));
+            assert_eq!(ssal.renounce_role(Role::Admin), Err(Error::AdminRoleReserved));
+            assert_eq!(
+                ssal.set_role_admin(Role::Admin, Role::Arbiter),
+                Err(Error::AdminRoleReserved)
+            );
+            assert!(ssal.has_role(Role::Admin, accounts.alice));
+            assert!(!ssal.has_role(Role::Admin, accounts.bob));
+
+            // Admins lose control of a role handed to another admin role
+            assert_eq!(ssal.set_role_admin(Role::Oracle, Role::Arbiter), Ok(()));
+            assert_eq!(ssal.grant_role(Role::Oracle, accounts.bob), Err(Error::MissingRole));
+
+            // Unverified sellers cannot list while the policy requires it
+            assert_eq!(ssal.set_seller_policy(true), Ok(()));
+            assert_eq!(
+                ssal.create_contract(RICE, 10, 10_000, 10, 20),
+                Err(Error::SellerNotVerified)
+            );
+            assert_eq!(ssal.create_contracts(vec![rice_listing(20)]), Err(Error::SellerNotVerified));
+            assert_eq!(ssal.get_contract_count(), 0);
+        }
+
+        #[ink::test]
+        fn quantity_conversion_works() {
+            let sacks = Quantity { amount: 25, unit: Unit::Sack(80_000) };
+            assert_eq!(sacks.to_grams(), Some(2_000_000));
+            assert_eq!(
+                sacks.convert_to(Unit::Tonnes),
+                Some(Quantity { amount: 2, unit: Unit::Tonnes })
+            );
+            assert_eq!(
+                sacks.convert_to(Unit::Kilograms),
+                Some(Quantity { amount: 2_000, unit: Unit::Kilograms })
+            );
+            // Conversions must be exact
+            assert_eq!(sacks.convert_to(Unit::Sack(30_000)), None);
+            assert_eq!(sacks.convert_to(Unit::Sack(0)), None);
+            // Conversions must fit in grams
+            let huge = Quantity { amount: u64::MAX, unit: Unit::Tonnes };
+            assert_eq!(huge.to_grams(), None);
+            assert_eq!(huge.convert_to(Unit::Tonnes), None);
+        }
+
+        #[ink::test]
+        fn create_contract_per_unit_works() {
+            let mut ssal = new_market(100_000);
+
+            // 3 tonnes at 2_000 per tonne
+            let tonnes = Quantity { amount: 3, unit: Unit::Tonnes };
+            assert_eq!(ssal.create_contract_per_unit(RICE, 10, 2_000, tonnes, 20), Ok(0));
+            assert_eq!(ssal.get_total(0), Some(6_000));
+            assert_eq!(ssal.get_unit_price(0), Some(2_000));
+            assert_eq!(ssal.get_quantity(0), Some(tonnes));
+            assert_eq!(ssal.get_weight(0), Some(3_000_000));
+
+            // Plain listings are quantities in grams
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(1));
+            assert_eq!(ssal.get_unit_price(1), None);
+            assert_eq!(ssal.get_quantity(1), Some(Quantity { amount: 10, unit: Unit::Grams }));
+
+            // Overflowing weights and totals are rejected
+            let huge = Quantity { amount: u64::MAX, unit: Unit::Tonnes };
+            assert_eq!(
+                ssal.create_contract_per_unit(RICE, 10, 1, huge, 20),
+                Err(Error::Overflow)
+            );
+            assert_eq!(
+                ssal.create_contract_per_unit(RICE, 10, Balance::MAX, tonnes, 20),
+                Err(Error::Overflow)
+            );
+
+            // Per-unit listings are quoted in the unit of their series
+            let kilograms = Quantity { amount: 3_000, unit: Unit::Kilograms };
+            assert_eq!(
+                ssal.create_contract_per_unit(RICE, 10, 2, kilograms, 20),
+                Err(Error::UnitMismatch)
+            );
+            assert_eq!(ssal.get_contract_count(), 2);
+
+            // Empty sacks are rejected rather than weighing nothing
+            let empty = Quantity { amount: 3, unit: Unit::Sack(0) };
+            assert_eq!(
+                ssal.create_contract_per_unit(RICE, 10, 2_000, empty, 20),
+                Err(Error::InvalidUnit)
+            );
+            assert_eq!(
+                ssal.register_series(String::from("Indica"), 2, String::from("Incheon"), Unit::Sack(0)),
+                Err(Error::InvalidUnit)
+            );
+            assert_eq!(ssal.get_contract_count(), 2);
+            assert_eq!(ssal.get_series_count(), 1);
+        }
+
+        #[ink::test]
+        fn overflow_fails() {
+            let mut ssal = new_market(Balance::MAX);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // The whole supply can move around without overflowing
+            assert_eq!(ssal.transfer(accounts.bob, Balance::MAX, Vec::new()), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), Balance::MAX);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.transfer(accounts.bob, Balance::MAX, Vec::new()), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), Balance::MAX);
+
+            // Price and total cannot add up past Balance::MAX
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.create_contract(RICE, Balance::MAX, 1, 10, 20), Ok(0));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.buy_contract(0), Err(Error::Overflow));
+            assert_eq!(ssal.balance_of(accounts.bob), Balance::MAX);
+
+            // Credits cannot push a balance past Balance::MAX
+            ssal.balances.insert(accounts.charlie, &Balance::MAX);
+            assert_eq!(
+                ssal.transfer(accounts.charlie, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("Overflow")))
+            );
+            assert_eq!(ssal.balance_of(accounts.charlie), Balance::MAX);
+            assert_eq!(ssal.balance_of(accounts.bob), Balance::MAX);
+
+            // Counters cannot wrap around
+            ssal.holder_count.set(&u64::MAX);
+            assert_eq!(
+                ssal.transfer(accounts.eve, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("Overflow")))
+            );
+            assert_eq!(ssal.balance_of(accounts.eve), 0);
+            assert_eq!(ssal.balance_of(accounts.bob), Balance::MAX);
+            ssal.holder_count.set(&3);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            ssal.contract_count = ContractId::MAX;
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Err(Error::Overflow));
+            assert_eq!(ssal.get_seller(ContractId::MAX), None);
+            ssal.series_count.set(&SeriesId::MAX);
+            assert_eq!(
+                ssal.register_series(
+                    String::from("Indica"),
+                    2,
+                    String::from("Incheon"),
+                    Unit::Sack(80_000),
+                ),
+                Err(Error::Overflow)
+            );
+        }
+
+        #[ink::test]
+        fn settle_max_balance_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(Balance::MAX);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+
+            // A Balance::MAX lot delivered short and wet still settles exactly
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 0, Balance::MAX, 3, 1), Ok(0));
+            assert_eq!(ssal.set_quality(0, rice_spec(), rice_schedule()), Ok(()));
+            assert_eq!(ssal.set_inspector(0, accounts.charlie, 0), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.django), Balance::MAX);
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let reading = QualitySpec { moisture: 1_600, ..rice_spec() };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.attest_delivery(0, 2, Some(reading), Hash::from([7; 32])), Ok(()));
+
+            let delivered = Balance::MAX / 3 * 2;
+            let payout = delivered - delivered / 100;
+            assert_eq!(ssal.balance_of(accounts.bob), payout);
+            assert_eq!(ssal.balance_of(accounts.alice), Balance::MAX - payout);
+            assert_eq!(ssal.balance_of(accounts.django), 0);
+        }
+
+        /// We test a simple use case of our contract.
+        #[ink::test]
+        fn listing_deposit_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            let rules = ListingRules {
+                deposit: 100,
+                min_weight: 10,
+                min_total: 1_000,
+                max_finality_horizon: Some(10),
+            };
+            assert_eq!(ssal.set_listing_rules(rules), Ok(()));
+            assert_eq!(ssal.get_listing_rules(), rules);
+
+            // Bob puts up a deposit for each listing
+            assert_eq!(ssal.transfer(accounts.bob, 1_000, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 3]), Ok(vec![0, 1, 2]));
+            assert_eq!(ssal.balance_of(accounts.bob), 700);
+            assert_eq!(ssal.deposit_of(0), 100);
+            assert_eq!(ssal.total_deposits(), 300);
+            assert_eq!(ssal.check_escrow(), Ok(()));
+
+            // Returned on sale...
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 700 + 10 + 100);
+            assert_eq!(ssal.deposit_of(0), 0);
+
+            // ...on cancellation...
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.cancel_contract(1), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 910);
+            assert_eq!(ssal.get_status(1), Some(ContractStatus::Cancelled));
+            assert_eq!(ssal.portfolio_of(accounts.bob).exposure.open_listings, 1);
+
+            // ...and on expiry, to anyone's call
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.expire_contract(2), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 1_010);
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Cancelled, 0, 10), vec![1, 2]);
+            assert_eq!(ssal.open_listings(0, 10), Vec::<ContractId>::new());
+            assert_eq!(ssal.total_deposits(), 0);
+            assert_eq!(ssal.check_escrow(), Ok(()));
+            assert!(ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn listing_deposit_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let rules = ListingRules {
+                deposit: 100,
+                min_weight: 10,
+                min_total: 1_000,
+                max_finality_horizon: Some(10),
+            };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.set_listing_rules(rules), Err(Error::NotOwner));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.set_listing_rules(rules), Ok(()));
+
+            // Each rule rejects its own field
+            assert_eq!(ssal.create_contract(RICE, 0, 1_000, 9, 1), Err(Error::WeightTooLow));
+            assert_eq!(ssal.create_contract(RICE, 0, 999, 10, 1), Err(Error::TotalTooLow));
+            assert_eq!(ssal.create_contract(RICE, 0, 1_000, 10, 11), Err(Error::FinalityTooFar));
+            assert_eq!(ssal.create_contract(RICE, 0, 1_000, 10, 10), Ok(0));
+
+            // Sellers must be able to put up every deposit
+            assert_eq!(ssal.transfer(accounts.bob, 150, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Err(Error::InsufficientBalance));
+            assert_eq!(ssal.create_contract(RICE, 0, 1_000, 10, 1), Ok(1));
+            assert_eq!(ssal.create_contract(RICE, 0, 1_000, 10, 1), Err(Error::InsufficientBalance));
+
+            // Only open listings are cancelled, by their seller or once expired
+            assert_eq!(ssal.cancel_contract(0), Err(Error::OnlySellerCanModify));
+            assert_eq!(ssal.cancel_contract(2), Err(Error::ContractNotFound));
+            assert_eq!(ssal.expire_contract(1), Err(Error::ListingNotExpired));
+            assert_eq!(ssal.cancel_contract(1), Ok(()));
+            assert_eq!(ssal.cancel_contract(1), Err(Error::ContractCancelled));
+            assert_eq!(ssal.expire_contract(1), Err(Error::ContractCancelled));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(1), Err(Error::ContractCancelled));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.cancel_contract(0), Err(Error::ContractAlreadyBought));
+        }
+
+        #[ink::test]
+        fn archive_contract_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 3]), Ok(vec![0, 1, 2]));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.cancel_contract(1), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+            assert_eq!(ssal.get_closed_at(0), Some(2));
+            let record = ssal.get_contract(0).unwrap();
+
+            for _ in 0..ARCHIVE_RETENTION {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            // Anyone can archive closed contracts
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.archive_contract(0), Ok(()));
+            assert_eq!(ssal.archive_contract(1), Ok(()));
+            assert_eq!(ssal.get_archive_commitment(0), Some(record.commitment(0)));
+            assert_ne!(record.commitment(1), record.commitment(0));
+            assert_eq!(ssal.get_contract(0), Err(Error::ContractNotFound));
+            assert_eq!(ssal.get_status(0), None);
+            assert_eq!(ssal.get_price(0), None);
+            assert_eq!(ssal.get_closed_at(0), None);
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Finalized, 0, 10), Vec::<ContractId>::new());
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Cancelled, 0, 10), Vec::<ContractId>::new());
+            assert_eq!(ssal.open_listings(0, 10), vec![2]);
+            assert!(ssal.audit().is_sound());
+
+            // Archives travel with a snapshot and keep their ids taken
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let contracts = ssal.export_contracts(0, 100).unwrap();
+            let archives = ssal.export_archives(0, 100).unwrap();
+            assert_eq!(contracts.len(), 1);
+            assert_eq!(archives.len(), 2);
+            let series = ssal.get_series(RICE).unwrap();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let mut copy = SsalCommods::new_from_snapshot();
+            assert_eq!(copy.import_series(vec![series]), Ok(()));
+            assert_eq!(copy.import_contracts(contracts), Ok(()));
+            assert_eq!(copy.import_archives(archives), Ok(()));
+            assert_eq!(copy.get_archive_commitment(0), Some(record.commitment(0)));
+            assert_eq!(copy.get_contract_count(), 3);
+        }
+
+        #[ink::test]
+        fn archive_contract_fails() {
+            let mut ssal = new_market(100_000);
+
+            assert_eq!(ssal.archive_contract(0), Err(Error::ContractNotFound));
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+
+            // Open and bought contracts are not closed yet
+            assert_eq!(ssal.archive_contract(0), Err(Error::ContractNotClosed));
+            assert_eq!(ssal.archive_contract(1), Err(Error::ContractNotClosed));
+
+            // Closed contracts are kept for the retention period
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+            for _ in 1..ARCHIVE_RETENTION {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(ssal.archive_contract(0), Err(Error::RetentionPeriodNotOver));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            // An inconsistent status index fails instead of panicking
+            ssal.corrupt_status_count(ContractStatus::Finalized, 0);
+            assert_eq!(ssal.archive_contract(0), Err(Error::Overflow));
+            ssal.corrupt_status_count(ContractStatus::Finalized, 2);
+            assert_eq!(ssal.archive_contract(0), Err(Error::ContractNotFound));
+            ssal.corrupt_status_count(ContractStatus::Finalized, 1);
+            assert_eq!(ssal.archive_contract(0), Ok(()));
+            assert_eq!(ssal.archive_contract(0), Err(Error::ContractNotFound));
+        }
+
+        #[ink::test]
+        fn buy_contract_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice owns all the tokens on contract instantiation
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
+            // Bob does not owns tokens
+            assert_eq!(ssal.balance_of(accounts.bob), 0);
+
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+
+            // Transfer funds to bob so he can purchase the contract.
+            // Make sure bob has 0 cash by default.
+            assert_eq!(ssal.balance_of(accounts.bob), 0);
+            // Alice transfers 10 tokens to Bob.
+            assert_eq!(ssal.transfer(accounts.bob, 10_000, Vec::new()), Ok(()));
+            // Bob owns 10 tokens.
+            assert_eq!(ssal.balance_of(accounts.bob), 10_000);
+            
+            // Try to buy in correct case 
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+        }
+
+        // Test possible failiures for buy_contract
+        #[ink::test]
+        fn buy_contract_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+
+            // Alice transfers 10 tokens to Bob.
+            assert_eq!(ssal.transfer(accounts.bob, 10_000, Vec::new()), Ok(()));
+            
+            // Try to buy in correct case 
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            // Try to buy the contract that has just been bought
+            assert_eq!(ssal.buy_contract(0), Err(Error::ContractAlreadyBought));
+            // Try to buy a contract that does not exist
+            assert_eq!(ssal.buy_contract(1), Err(Error::ContractNotFound));
+            // Try to buy variations of contracts that cost too much
+            assert_eq!(ssal.create_contract(RICE, 100_000, 100_000, 10, 20), Ok(1));
+            assert_eq!(ssal.create_contract(RICE, 100_000, 0, 10, 20), Ok(2));
+            assert_eq!(ssal.create_contract(RICE, 0, 100_000, 10, 20), Ok(3));
+            assert_eq!(ssal.buy_contract(1), Err(Error::InsufficientBalance));
+            assert_eq!(ssal.buy_contract(2), Err(Error::InsufficientBalance));
+            assert_eq!(ssal.buy_contract(3), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn buy_contracts_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            let listings = vec![rice_listing(20); 4];
+            assert_eq!(ssal.create_contracts(listings), Ok(vec![0, 1, 2, 3]));
+            assert_eq!(ssal.transfer(accounts.bob, 31_000, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+            // Atomic batches buy every contract
+            assert_eq!(
+                ssal.buy_contracts(vec![0, 1], BatchMode::Atomic),
+                Ok(vec![Ok(()), Ok(())])
+            );
+            assert_eq!(ssal.get_buyer(1), Some(accounts.bob));
+            assert_eq!(ssal.balance_of(accounts.bob), 31_000 - 2 * 10_010);
+
+            // Best effort batches report failures per contract
+            assert_eq!(
+                ssal.buy_contracts(vec![1, 2, 3, 4], BatchMode::BestEffort),
+                Ok(vec![
+                    Err(Error::ContractAlreadyBought),
+                    Ok(()),
+                    Err(Error::InsufficientBalance),
+                    Err(Error::ContractNotFound),
+                ])
+            );
+            assert_eq!(ssal.get_buyer(2), Some(accounts.bob));
+            assert_eq!(ssal.get_buyer(3), None);
+        }
+
+        #[ink::test]
+        fn buy_contracts_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            let listings = vec![rice_listing(20); 3];
+            assert_eq!(ssal.create_contracts(listings), Ok(vec![0, 1, 2]));
+            assert_eq!(ssal.transfer(accounts.bob, 20_020, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+
+            // Each contract is affordable on its own but not all of them together
+            assert_eq!(
+                ssal.buy_contracts(vec![0, 1, 2], BatchMode::Atomic),
+                Err(Error::InsufficientBalance)
+            );
+            // Repeated and missing contracts reject the whole batch
+            assert_eq!(
+                ssal.buy_contracts(vec![0, 0], BatchMode::Atomic),
+                Err(Error::ContractAlreadyBought)
+            );
+            assert_eq!(
+                ssal.buy_contracts(vec![0, 5], BatchMode::Atomic),
+                Err(Error::ContractNotFound)
+            );
+            assert_eq!(ssal.get_buyer(0), None);
+            assert_eq!(ssal.balance_of(accounts.bob), 20_020);
+
+            assert_eq!(
+                ssal.buy_contracts(vec![0; MAX_BATCH_SIZE + 1], BatchMode::BestEffort),
+                Err(Error::BatchTooLarge)
+            );
+        }
+
+        #[ink::test]
+        fn settlement_asset_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            let token = SettlementAsset::Psp22(accounts.eve);
+
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            assert_eq!(ssal.get_settlement_asset(0), Some(SettlementAsset::Internal));
+            assert_eq!(ssal.set_settlement_asset(0, token), Ok(()));
+            assert_eq!(ssal.get_settlement_asset(0), Some(token));
+            assert_eq!(ssal.get_contract(0).unwrap().settlement_asset, Some(token));
+
+            // Switching back to the internal token clears the entry
+            assert_eq!(ssal.set_settlement_asset(0, SettlementAsset::Internal), Ok(()));
+            assert_eq!(ssal.get_settlement_asset(0), Some(SettlementAsset::Internal));
+            assert_eq!(ssal.get_contract(0).unwrap().settlement_asset, None);
+
+            // Contracts settling in the internal token are escrowed as before
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.total_escrow(), 10_000);
+            assert_eq!(ssal.locked_balance_of(accounts.alice), 10_000);
+            assert!(ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn settlement_asset_fails() {
+            let mut ssal = new_market(100_000);
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let token = SettlementAsset::Psp22(accounts.eve);
+
+            assert_eq!(ssal.set_settlement_asset(0, token), Err(Error::ContractNotFound));
+            assert_eq!(ssal.get_settlement_asset(0), None);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            let own_token = SettlementAsset::Psp22(ink::env::account_id::<ink::env::DefaultEnvironment>());
+            assert_eq!(ssal.set_settlement_asset(0, own_token), Err(Error::InvalidSettlementAsset));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.set_settlement_asset(0, token), Err(Error::OnlySellerCanModify));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.set_settlement_asset(0, token), Err(Error::ContractAlreadyBought));
+            assert_eq!(ssal.get_settlement_asset(0), Some(SettlementAsset::Internal));
+        }
+
+        #[ink::test]
+        fn native_settlement_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            // The contract account is endowed with the existential deposit
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 1_000_000);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 10_000_000);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+
+            // Alice lists a lot settling in the native currency, inspected by Charlie
+            assert_eq!(ssal.create_contract(RICE, 2_000_000, 5_000_000, 10, 1), Ok(0));
+            assert_eq!(ssal.set_settlement_asset(0, SettlementAsset::Native), Ok(()));
+            assert_eq!(ssal.set_inspector(0, accounts.charlie, 500), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(7_000_000);
+            assert_eq!(ssal.buy_contract_native(0), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            let balance = |account| {
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(account).unwrap()
+            };
+            assert_eq!(balance(accounts.alice), 1_000_000 + 2_000_000);
+            assert_eq!(ssal.get_balance(), 1_000_000 + 5_000_000);
+            assert_eq!(ssal.native_escrow(), 5_000_000);
+            assert_eq!(ssal.escrow_of(0), 5_000_000);
+            assert_eq!(ssal.total_escrow(), 0);
+            assert_eq!(ssal.balance_of(accounts.bob), 0);
+            assert!(ssal.audit().is_sound());
+
+            // Settling pays the seller and the inspector fee right away
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.attest_delivery(0, 10, None, Hash::from([7; 32])), Ok(()));
+            assert_eq!(ssal.is_finalized(0), Some(true));
+            assert_eq!(balance(accounts.alice), 3_000_000 + 5_000_000 - 500);
+            assert_eq!(balance(accounts.charlie), 1_000 + 500);
+            assert_eq!(ssal.native_escrow(), 0);
+            assert_eq!(ssal.unclaimed_native_of(accounts.charlie), 0);
+            assert_eq!(ssal.get_balance(), 1_000_000);
+            assert!(ssal.audit().is_sound());
+
+            // A payout the runtime rejected is held for Eve, who can claim it to
+            // another account
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 1_000_300);
+            assert_eq!(ssal.defer_native(&accounts.eve, 300), Ok(()));
+            assert_eq!(ssal.unclaimed_native_of(accounts.eve), 300);
+            assert_eq!(ssal.total_unclaimed_native(), 300);
+            assert!(ssal.audit().is_sound());
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.claim_native_to(accounts.frank), Ok(300));
+            assert_eq!(balance(accounts.frank), 300);
+            assert_eq!(ssal.unclaimed_native_of(accounts.eve), 0);
+            assert_eq!(ssal.claim_native(), Ok(0));
+
+            // Held amounts are sent along with the next payout
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 1_000_500);
+            assert_eq!(ssal.defer_native(&accounts.eve, 200), Ok(()));
+            assert_eq!(ssal.pay_native(&accounts.eve, 300), Ok(()));
+            assert_eq!(balance(accounts.eve), 500);
+            assert_eq!(ssal.unclaimed_native_of(accounts.eve), 0);
+            assert_eq!(ssal.total_unclaimed_native(), 0);
+            assert_eq!(ssal.get_balance(), 1_000_000);
+            assert!(ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn native_settlement_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 1_000_000);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 10_000_000);
+            let mut ssal = new_market(100_000);
+
+            assert_eq!(ssal.create_contract(RICE, 2_000_000, 5_000_000, 10, 1), Ok(0));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(1));
+            assert_eq!(ssal.set_settlement_asset(0, SettlementAsset::Native), Ok(()));
+
+            // Each contract can only be bought in the asset it settles in
+            assert_eq!(ssal.buy_contract(0), Err(Error::SettlementAssetMismatch));
+            assert_eq!(
+                ssal.buy_contracts(vec![1, 0], BatchMode::Atomic),
+                Err(Error::SettlementAssetMismatch)
+            );
+            assert_eq!(ssal.buy_contract_native(1), Err(Error::SettlementAssetMismatch));
+
+            // The exact price plus total must be sent
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(6_999_999);
+            assert_eq!(ssal.buy_contract_native(0), Err(Error::InvalidPayment));
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(7_000_000);
+            assert_eq!(ssal.buy_contract_native(0), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+
+            // The contract account never pays out its own existential deposit
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 5_999_999);
+            assert_eq!(
+                ssal.audit().violations,
+                vec![Violation::NativeShortfall { spendable: 4_999_999, outstanding: 5_000_000 }]
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Err(Error::NativeTransferFailed));
+        }
+
+        #[ink::test]
+        fn mint_and_burn_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = SsalCommods::new_capped(100_000, 150_000).unwrap();
+            assert_eq!(ssal.get_supply_cap(), Some(150_000));
+
+            // The admin mints up to the cap
+            assert_eq!(ssal.mint(accounts.bob, 50_000), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 50_000);
+            assert_eq!(ssal.total_supply(), 150_000);
+
+            // Holders burn their own tokens, or those they are allowed to spend
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.burn(20_000), Ok(()));
+            assert_eq!(ssal.approve(accounts.charlie, 10_000), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.burn_from(accounts.bob, 6_000), Ok(()));
+            assert_eq!(ssal.allowance(accounts.bob, accounts.charlie), 4_000);
+            assert_eq!(ssal.balance_of(accounts.bob), 24_000);
+            assert_eq!(ssal.total_supply(), 124_000);
+
+            // Burned supply can be minted again
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.mint(accounts.alice, 26_000), Ok(()));
+            assert_eq!(ssal.total_supply(), 150_000);
+            assert!(ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn mint_and_burn_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            assert!(matches!(SsalCommods::new_capped(100_001, 100_000), Err(Error::CapExceeded)));
+            let mut ssal = SsalCommods::new_capped(100_000, 100_000).unwrap();
+            assert_eq!(
+                ssal.register_series(String::from("Japonica"), 1, String::from("Busan"), Unit::Tonnes),
+                Ok(RICE)
+            );
+            assert_eq!(ssal.mint(accounts.bob, 1), Err(Error::CapExceeded));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.mint(accounts.bob, 1), Err(Error::NotOwner));
+            assert_eq!(ssal.burn(1), Err(Error::InsufficientBalance));
+            assert_eq!(ssal.burn_from(accounts.alice, 1), Err(Error::InsufficientAllowance));
+
+            // Escrow pooled on the contract account can never be burned
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 20), Ok(0));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.burn_from(accounts.django, 10_000), Err(Error::PoolNotBurnable));
+            assert_eq!(ssal.escrow_of(0), 10_000);
+            assert_eq!(ssal.balance_of(accounts.django), 10_000);
+
+            assert_eq!(ssal.pause(PauseScope::Transfers), Ok(()));
+            assert_eq!(ssal.burn(1), Err(Error::Paused));
+            assert_eq!(ssal.burn_from(accounts.bob, 1), Err(Error::Paused));
+        }
+
+        #[ink::test]
+        fn wrapped_native_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 1_000_000);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 10_000_000);
+            let mut ssal = new_market(100_000);
+
+            // Bob wraps native currency into tokens
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3_000_000);
+            assert_eq!(ssal.deposit(), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(ssal.balance_of(accounts.bob), 3_000_000);
+            assert_eq!(ssal.total_supply(), 100_000 + 3_000_000);
+            assert_eq!(ssal.wrapped_reserve(), 3_000_000);
+            assert!(ssal.audit().is_sound());
+
+            // Withdrawals stay open while transfers are paused
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.pause(PauseScope::Transfers), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.withdraw(1_000_000), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 2_000_000);
+            assert_eq!(ssal.total_supply(), 100_000 + 2_000_000);
+            assert_eq!(ssal.wrapped_reserve(), 2_000_000);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(10_000_000 - 3_000_000 + 1_000_000)
+            );
+            assert_eq!(ssal.get_balance(), 1_000_000 + 2_000_000);
+            assert!(ssal.audit().is_sound());
+
+            type Event = <SsalCommods as ink::reflect::ContractEventBase>::Type;
+            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let burned = <Event as scale::Decode>::decode(&mut &emitted_events.last().unwrap().data[..]);
+            assert!(matches!(
+                burned,
+                Ok(Event::Transfer(Transfer { from: Some(from), to: None, value: 1_000_000 }))
+                    if from == accounts.bob
+            ));
+        }
+
+        #[ink::test]
+        fn wrapped_native_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 1_000_000);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.bob, 10_000_000);
+            let mut ssal = new_market(10_000_000);
+
+            assert_eq!(ssal.pause(PauseScope::Transfers), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(3_000_000);
+            assert_eq!(ssal.deposit(), Err(Error::Paused));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.unpause(PauseScope::Transfers), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::transfer_in::<ink::env::DefaultEnvironment>(3_000_000);
+            assert_eq!(ssal.deposit(), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+            assert_eq!(ssal.withdraw(3_000_001), Err(Error::InsufficientBalance));
+
+            // Tokens that were never deposited cannot be withdrawn
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.withdraw(3_000_001), Err(Error::InsufficientReserve));
+
+            // The contract account never pays out its own existential deposit
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(accounts.django, 3_999_999);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.withdraw(3_000_000), Err(Error::NativeTransferFailed));
+        }
+
+        #[ink::test]
+        fn pause_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.grant_role(Role::Pauser, accounts.eve), Ok(()));
+
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            assert_eq!(ssal.transfer(accounts.bob, 30_000, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+
+            // Pausing one scope leaves the others running
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.pause(PauseScope::Settlement), Ok(()));
+            assert!(ssal.is_paused(PauseScope::Settlement));
+            assert!(!ssal.is_paused(PauseScope::Buying));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.buy_contract(1), Ok(()));
+            assert_eq!(ssal.transfer(accounts.alice, 1_000, Vec::new()), Ok(()));
+
+            // Buyers take their escrow back while settlement is paused, until the
+            // finality block passes
+            assert_eq!(ssal.balance_of(accounts.bob), 8_980);
+            assert_eq!(ssal.refund_escrow(0), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 18_980);
+            assert_eq!(ssal.balance_of(accounts.alice), 71_020);
+            assert_eq!(ssal.escrow_of(0), 0);
+            assert_eq!(ssal.get_status(0), Some(ContractStatus::Finalized));
+            assert!(ssal.audit().is_sound());
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.refund_escrow(1), Err(Error::FinalityBlockPassed));
+            assert_eq!(ssal.escrow_of(1), 10_000);
+
+            // Settlement resumes once unpaused
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.unpause(PauseScope::Settlement), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.finalize(1), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.alice), 81_020);
+        }
+
+        #[ink::test]
+        fn pause_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(0));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(1));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(2));
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+            assert_eq!(ssal.set_inspector(2, accounts.charlie, 100), Ok(()));
+            assert_eq!(ssal.buy_contract(2), Ok(()));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(3));
+            assert_eq!(ssal.buy_contract(3), Ok(()));
+
+            // Only the owner and pausers pause
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.pause(PauseScope::Buying), Err(Error::MissingRole));
+            assert_eq!(ssal.unpause(PauseScope::Buying), Err(Error::MissingRole));
+
+            // Refunds are only open while settlement is paused
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.refund_escrow(0), Err(Error::NotPaused));
+
+            for scope in [
+                PauseScope::Listing,
+                PauseScope::Buying,
+                PauseScope::Settlement,
+                PauseScope::Transfers,
+            ] {
+                assert_eq!(ssal.pause(scope), Ok(()));
+            }
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Err(Error::Paused));
+            assert_eq!(ssal.buy_contract(1), Err(Error::Paused));
+            assert_eq!(
+                ssal.buy_contracts(vec![1], BatchMode::BestEffort),
+                Ok(vec![Err(Error::Paused)])
+            );
+            assert_eq!(
+                ssal.transfer(accounts.bob, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("Paused")))
+            );
+            assert_eq!(
+                ssal.transfer_from(accounts.alice, accounts.bob, 0, Vec::new()),
+                Err(PSP22Error::Custom(String::from("Paused")))
+            );
+
+            // Attested contracts wait for settlement to resume
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.attest_delivery(2, 10, None, Hash::from([7; 32])), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.refund_escrow(2), Err(Error::AlreadyAttested));
+            assert_eq!(ssal.escrow_of(2), 10_000);
+            assert!(ssal.get_attestation(2).is_some());
+
+            // Only the buyer of a bought, unsettled contract gets a refund
+            assert_eq!(ssal.refund_escrow(4), Err(Error::ContractNotFound));
+            assert_eq!(ssal.refund_escrow(1), Err(Error::ContractNotPurchased));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.refund_escrow(0), Err(Error::OnlyBuyerCanFinalize));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.refund_escrow(0), Ok(()));
+            assert_eq!(ssal.refund_escrow(0), Err(Error::ContractAlreadyFinalized));
+
+            // Past the finality block the seller may have delivered, so the escrow
+            // waits for settlement to resume
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(3), Err(Error::Paused));
+            assert_eq!(ssal.refund_escrow(3), Err(Error::FinalityBlockPassed));
+            assert_eq!(ssal.escrow_of(3), 10_000);
+        }
+
+        #[ink::test]
+        fn migrate_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let pool = accounts.django;
+
+            // Storage left behind by the original code: Alice was minted the supply,
+            // Eve listed 52 lots, Bob bought one and Charlie bought and settled another
+            let mut legacy = baseline::SsalCommods {
+                total_supply: 100_000,
+                ..Default::default()
+            };
+            legacy.balances.insert(accounts.alice, &100_000);
+            legacy.transfer(accounts.alice, accounts.bob, 30_000);
+            legacy.transfer(accounts.alice, accounts.charlie, 30_000);
+            for _ in 0..MAX_BATCH_SIZE {
+                legacy.list(accounts.eve, 5_000);
+            }
+            let bought = legacy.list(accounts.eve, 5_000);
+            let settled = legacy.list(accounts.eve, 5_000);
+            legacy.buy(bought, accounts.bob, pool);
+            legacy.buy(settled, accounts.charlie, pool);
+            legacy.finalize(settled, pool);
+            let root = <SsalCommods as ink::storage::traits::StorageKey>::KEY;
+            ink::env::set_contract_storage(&root, &legacy);
+
+            // The current code decodes the original root storage cell in place, and
+            // keeps nothing else in it
+            let mut ssal: SsalCommods = ink::env::get_contract_storage(&root).unwrap().unwrap();
+            let mut current = Vec::new();
+            ink::storage::traits::Storable::encode(&ssal, &mut current);
+            let mut original = Vec::new();
+            ink::storage::traits::Storable::encode(&legacy, &mut original);
+            assert_eq!(current, original);
+            assert_eq!(ssal.get_contract_count(), 52);
+            assert_eq!(ssal.total_supply(), 100_000);
+            assert_eq!(ssal.balance_of(accounts.bob), 25_000);
+            assert_eq!(ssal.get_total(bought), Some(5_000));
+            assert_eq!(ssal.get_owner(), None);
+            assert_eq!(ssal.get_storage_version(), 0);
+            assert_eq!(ssal.escrow_of(bought), 0);
+            assert_eq!(ssal.check_escrow(), Err(Error::EscrowMismatch));
+
+            // Frank claims the ownerless deployment, which only the owner migrates
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(ssal.migrate(), Err(Error::NotOwner));
+            assert_eq!(ssal.claim_ownership(), Ok(()));
+            assert_eq!(ssal.get_owner(), Some(accounts.frank));
+            assert!(ssal.has_role(Role::Admin, accounts.frank));
+            assert_eq!(ssal.renounce_ownership(), Err(Error::MigrationPending));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.claim_ownership(), Err(Error::OwnershipNotClaimable));
+            assert_eq!(ssal.migrate(), Err(Error::NotOwner));
+
+            // Contracts are backfilled in bounded batches
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(ssal.migrate(), Ok(false));
+            assert_eq!(ssal.get_storage_version(), 0);
+            assert_eq!(ssal.migrate(), Ok(true));
+            assert_eq!(ssal.get_storage_version(), 1);
+
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Open, 0, 100).len(), 50);
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Bought, 0, 10), vec![bought]);
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Finalized, 0, 10), vec![settled]);
+            assert_eq!(ssal.contracts_by_seller(accounts.eve, 0, 100).len(), 52);
+            assert_eq!(ssal.contracts_by_buyer(accounts.bob, 0, 10), vec![bought]);
+            assert_eq!(ssal.get_quantity(bought), Some(Quantity { amount: 1_000, unit: Unit::Grams }));
+            assert_eq!(ssal.escrow_of(bought), 5_000);
+            assert_eq!(ssal.total_escrow(), 5_000);
+            assert_eq!(ssal.locked_balance_of(accounts.bob), 5_000);
+            assert_eq!(ssal.portfolio_of(accounts.eve).exposure.open_listings, 50);
+            assert_eq!(ssal.portfolio_of(accounts.eve).exposure.receivable, 5_000);
+            assert_eq!(ssal.check_escrow(), Ok(()));
+
+            // Alice only ever held tokens, so she is missing from the holders
+            // until backfilled
+            assert_eq!(
+                ssal.audit().violations,
+                vec![Violation::SupplyMismatch { total_supply: 100_000, sum_of_balances: 60_000 }]
+            );
+            assert_eq!(ssal.backfill_holders(vec![accounts.alice, accounts.frank, accounts.bob]), Ok(()));
+            assert!(ssal.audit().is_sound());
+
+            // Migrating again changes nothing
+            assert_eq!(ssal.migrate(), Ok(true));
+            assert_eq!(ssal.total_escrow(), 5_000);
+            assert_eq!(ssal.contracts_by_seller(accounts.eve, 0, 100).len(), 52);
+
+            // Legacy contracts settle like any other
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            for _ in 0..5 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(ssal.finalize(bought), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.eve), 10_000);
+            assert!(ssal.audit().is_sound());
+
+            // The owner opens a series, which takes new listings and buyers
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(
+                ssal.register_series(
+                    String::from("Japonica"),
+                    1,
+                    String::from("Busan"),
+                    Unit::Tonnes,
+                ),
+                Ok(RICE)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.create_contract(RICE, 10, 5_000, 1_000, 100), Ok(52));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.buy_contract(52), Ok(()));
+            assert_eq!(ssal.contracts_by_series(RICE, 0, 10), vec![52]);
+            assert_eq!(ssal.get_status(52), Some(ContractStatus::Bought));
+            assert!(ssal.audit().is_sound());
+
+            // Ownership cannot be claimed again once migrated, even if renounced
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.frank);
+            assert_eq!(ssal.renounce_ownership(), Ok(()));
+            assert_eq!(ssal.claim_ownership(), Err(Error::OwnershipNotClaimable));
+            assert_eq!(ssal.migrate(), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn migrate_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Only the owner upgrades and migrates
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.upgrade(Hash::from([1; 32])), Err(Error::NotOwner));
+            assert_eq!(ssal.migrate(), Err(Error::NotOwner));
+
+            // Deployments of this code always had an owner to claim from
+            assert_eq!(ssal.claim_ownership(), Err(Error::OwnershipNotClaimable));
+
+            // Holders are backfilled in bounded batches
+            assert_eq!(
+                ssal.backfill_holders(vec![accounts.bob; MAX_BATCH_SIZE + 1]),
+                Err(Error::BatchTooLarge)
+            );
+        }
+
+        #[ink::test]
+        fn snapshot_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 3]), Ok(vec![0, 1, 2]));
+            assert_eq!(ssal.transfer(accounts.bob, 30_000, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.buy_contracts(vec![0, 1], BatchMode::Atomic).map(|r| r.len()), Ok(2));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(1), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let series = vec![ssal.get_series(RICE).unwrap()];
+            let contracts = ssal.export_contracts(0, 100).unwrap();
+            let balances = ssal.export_balances(0, 100).unwrap();
+            assert_eq!(contracts.len(), 3);
+            assert_eq!(ssal.export_contracts(1, 1).unwrap(), contracts[1..2].to_vec());
+            assert!(!balances.iter().any(|(account, _)| *account == accounts.django));
+
+            // Seed a fresh deployment from the snapshot
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let mut copy = SsalCommods::new_from_snapshot();
+            assert!(copy.is_importing());
+            assert_eq!(copy.import_series(series), Ok(()));
+            assert_eq!(copy.import_contracts(contracts[..2].to_vec()), Ok(()));
+            assert_eq!(copy.import_contracts(contracts[2..].to_vec()), Ok(()));
+            assert_eq!(copy.import_balances(balances.clone()), Ok(()));
+            assert_eq!(copy.finish_import(), Ok(()));
+            assert!(!copy.is_importing());
+
+            assert_eq!(copy.export_contracts(0, 100), Ok(contracts));
+            assert_eq!(copy.export_balances(0, 100), Ok(balances));
+            assert_eq!(copy.total_supply(), 100_000);
+            assert_eq!(copy.balance_of(accounts.bob), 9_980);
+            assert_eq!(copy.balance_of(accounts.frank), 10_000);
+            assert_eq!(copy.escrow_of(0), 10_000);
+            assert_eq!(copy.get_status(2), Some(ContractStatus::Open));
+            assert_eq!(copy.contracts_by_series(RICE, 0, 10), vec![0, 1, 2]);
+            assert_eq!(copy.locked_balance_of(accounts.bob), 10_000);
+            assert!(copy.audit().is_sound());
+
+            // Imported contracts settle like any other
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(copy.finalize(0), Ok(()));
+            assert_eq!(copy.balance_of(accounts.alice), 90_020);
+        }
+
+        #[ink::test]
+        fn snapshot_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            let contracts = ssal.export_contracts(0, 100).unwrap();
+
+            // Only the owner exports and imports
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.export_contracts(0, 100), Err(Error::NotOwner));
+            assert_eq!(ssal.export_balances(0, 100), Err(Error::NotOwner));
+
+            // Contracts deployed with `new` accept no records
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.import_balances(vec![(accounts.bob, 1)]), Err(Error::ImportClosed));
+            assert_eq!(ssal.finish_import(), Err(Error::ImportClosed));
+
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let mut copy = SsalCommods::new_from_snapshot();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(copy.import_contracts(contracts.clone()), Err(Error::NotOwner));
+
+            // Records must come after what was imported, once
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(copy.import_contracts(contracts[1..].to_vec()), Ok(()));
+            assert_eq!(copy.import_contracts(contracts[..1].to_vec()), Err(Error::InvalidSnapshot));
+            assert_eq!(copy.import_contracts(contracts[1..].to_vec()), Err(Error::InvalidSnapshot));
+            assert_eq!(copy.import_archives(vec![(1, Hash::from([1; 32]))]), Err(Error::InvalidSnapshot));
+            assert_eq!(copy.import_balances(vec![(accounts.bob, 5)]), Ok(()));
+            assert_eq!(copy.import_balances(vec![(accounts.bob, 5)]), Err(Error::InvalidSnapshot));
+            assert_eq!(
+                copy.import_balances(vec![(accounts.eve, 1); MAX_BATCH_SIZE + 1]),
+                Err(Error::BatchTooLarge)
+            );
+
+            // Sealed snapshots reject further records
+            assert_eq!(copy.finish_import(), Ok(()));
+            assert_eq!(copy.import_contracts(contracts[1..].to_vec()), Err(Error::ImportClosed));
+            assert_eq!(copy.import_series(Vec::new()), Err(Error::ImportClosed));
+        }
+
+        #[ink::test]
+        fn finalize_many_works() {
+            let mut ssal = new_market(100_000);
+
+            let listings = vec![rice_listing(1); 4];
+            assert_eq!(ssal.create_contracts(listings), Ok(vec![0, 1, 2, 3]));
+            assert_eq!(
+                ssal.buy_contracts(vec![0, 1, 2], BatchMode::Atomic),
+                Ok(vec![Ok(()), Ok(()), Ok(())])
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            // Atomic batches reject every contract if one cannot settle
+            assert_eq!(
+                ssal.finalize_many(vec![0, 3], BatchMode::Atomic),
+                Err(Error::ContractNotPurchased)
+            );
+            assert_eq!(
+                ssal.finalize_many(vec![0, 0], BatchMode::Atomic),
+                Err(Error::ContractAlreadyFinalized)
+            );
+            assert_eq!(ssal.is_finalized(0), Some(false));
+
+            assert_eq!(
+                ssal.finalize_many(vec![0, 1], BatchMode::Atomic),
+                Ok(vec![Ok(()), Ok(())])
+            );
+            assert_eq!(ssal.is_finalized(1), Some(true));
+            assert_eq!(
+                ssal.finalize_many(vec![1, 2, 3], BatchMode::BestEffort),
+                Ok(vec![
+                    Err(Error::ContractAlreadyFinalized),
+                    Ok(()),
+                    Err(Error::ContractNotPurchased),
+                ])
+            );
+            assert_eq!(ssal.is_finalized(2), Some(true));
+        }
+
+        #[ink::test]
+        fn contract_indexes_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice lists three lots and Bob lists one
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 3]), Ok(vec![0, 1, 2]));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(3));
+            assert_eq!(ssal.contracts_by_seller(accounts.alice, 0, 10), vec![0, 1, 2]);
+            assert_eq!(ssal.contracts_by_seller(accounts.alice, 1, 1), vec![1]);
+            assert_eq!(ssal.contracts_by_seller(accounts.bob, 0, 10), vec![3]);
+            assert_eq!(ssal.open_listings(0, 10), vec![0, 1, 2, 3]);
+
+            // Alice buys Bob's lot and her own first lot
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(3), Ok(()));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.contracts_by_buyer(accounts.alice, 0, 10), vec![3, 0]);
+            assert_eq!(ssal.contracts_by_buyer(accounts.bob, 0, 10), Vec::<ContractId>::new());
+            assert_eq!(ssal.open_listings(0, 10), vec![2, 1]);
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Bought, 0, 10), vec![3, 0]);
+            assert_eq!(ssal.get_status(0), Some(ContractStatus::Bought));
+            assert_eq!(ssal.get_status(1), Some(ContractStatus::Open));
+            assert_eq!(ssal.get_status(4), None);
+
+            // Settling moves the lot out of the bought index
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(3), Ok(()));
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Bought, 0, 10), vec![0]);
+            assert_eq!(ssal.contracts_by_status(ContractStatus::Finalized, 0, 10), vec![3]);
+            assert_eq!(ssal.get_status(3), Some(ContractStatus::Finalized));
+
+            // Pages are bounded
+            assert_eq!(ssal.create_contracts(vec![rice_listing(20); 50]).map(|ids| ids.len()), Ok(50));
+            assert_eq!(ssal.create_contracts(vec![rice_listing(20); 50]).map(|ids| ids.len()), Ok(50));
+            assert_eq!(ssal.open_listings(0, u64::MAX).len() as u64, MAX_PAGE_SIZE);
+            assert_eq!(ssal.open_listings(100, u64::MAX).len(), 2);
+        }
+
+        #[ink::test]
+        fn search_listings_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            let listings = vec![
+                ListingParams { weight: 2_000_000, total: 5_000, ..rice_listing(100) },
+                ListingParams { weight: 500_000, total: 1_000, ..rice_listing(100) },
+                ListingParams { weight: 3_000_000, total: 9_000, ..rice_listing(20_000) },
+                ListingParams { weight: 1_000_000, total: 2_000, ..rice_listing(50) },
+            ];
+            assert_eq!(ssal.create_contracts(listings), Ok(vec![0, 1, 2, 3]));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 10, 1_000, 1_000_000, 100), Ok(4));
+
+            // At least a tonne, at most 6_000 total, settling within 10k blocks
+            let filter = ListingFilter {
+                min_weight: Some(1_000_000),
+                max_total: Some(6_000),
+                max_finality_block: Some(10_000),
+                ..Default::default()
+            };
+            let page = ssal.search_listings(filter, 0, 10);
+            let ids: Vec<ContractId> = page.listings.iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids, vec![0, 3, 4]);
+            assert_eq!(page.listings[0].1, ssal.get_contract(0).unwrap());
+            assert_eq!(page.next_cursor, None);
+
+            // Results are paginated by limit
+            let page = ssal.search_listings(filter, 0, 2);
+            assert_eq!(page.listings.len(), 2);
+            assert_eq!(page.next_cursor, Some(4));
+            let page = ssal.search_listings(filter, 4, 2);
+            assert_eq!(page.listings[0].0, 4);
+
+            // Bought contracts are no longer listed
+            let by_alice = ListingFilter { seller: Some(accounts.alice), ..filter };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            let page = ssal.search_listings(by_alice, 0, 10);
+            assert_eq!(page.listings.len(), 1);
+            assert_eq!(page.listings[0].0, 3);
+        }
+
+        #[ink::test]
+        fn portfolio_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+
+            // Bob lists two lots and Alice buys one of them
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            assert_eq!(ssal.portfolio_of(accounts.bob).exposure.open_listings, 2);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+
+            assert_eq!(ssal.locked_balance_of(accounts.alice), 10_000);
+            assert_eq!(
+                ssal.portfolio_of(accounts.alice),
+                Portfolio {
+                    free_balance: 100_000 - 10_010,
+                    exposure: Exposure {
+                        open_listings: 0,
+                        open_positions: 1,
+                        locked: 10_000,
+                        receivable: 0,
+                    },
+                }
+            );
+            assert_eq!(
+                ssal.portfolio_of(accounts.bob),
+                Portfolio {
+                    free_balance: 10,
+                    exposure: Exposure {
+                        open_listings: 1,
+                        open_positions: 0,
+                        locked: 0,
+                        receivable: 10_000,
+                    },
+                }
+            );
+
+            // Settlement releases the escrow on both sides
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+            assert_eq!(ssal.locked_balance_of(accounts.alice), 0);
+            assert_eq!(ssal.portfolio_of(accounts.alice).exposure, Exposure::default());
+            assert_eq!(ssal.portfolio_of(accounts.bob).free_balance, 10_010);
+            assert_eq!(ssal.portfolio_of(accounts.bob).exposure.receivable, 0);
+            assert_eq!(ssal.portfolio_of(accounts.bob).exposure.open_listings, 1);
+        }
+
+        #[ink::test]
+        fn escrow_ledger_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            assert_eq!(ssal.buy_contracts(vec![0, 1], BatchMode::Atomic).map(|r| r.len()), Ok(2));
+            assert_eq!(ssal.escrow_of(0), 10_000);
+            assert_eq!(ssal.total_escrow(), 20_000);
+            assert_eq!(ssal.check_escrow(), Ok(()));
+
+            // Tokens sent straight to the contract account are not escrowed
+            assert_eq!(ssal.transfer(accounts.django, 500, Vec::new()), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.django), 20_500);
+            assert_eq!(ssal.stray_tokens(), 500);
+            assert_eq!(ssal.check_escrow(), Err(Error::EscrowMismatch));
+
+            // Only the owner can recover them, and escrow is left untouched
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.sweep_stray_tokens(accounts.bob), Err(Error::NotOwner));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.sweep_stray_tokens(accounts.eve), Ok(500));
+            assert_eq!(ssal.balance_of(accounts.eve), 500);
+            assert_eq!(ssal.sweep_stray_tokens(accounts.eve), Ok(0));
+            assert_eq!(ssal.check_escrow(), Ok(()));
+
+            // Settlement clears the contract's escrow
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+            assert_eq!(ssal.escrow_of(0), 0);
+            assert_eq!(ssal.escrow_of(1), 10_000);
+            assert_eq!(ssal.total_escrow(), 10_000);
+            assert_eq!(ssal.check_escrow(), Ok(()));
+        }
+
+        #[ink::test]
+        fn audit_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert!(ssal.audit().is_sound());
+
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            assert_eq!(ssal.buy_contracts(vec![0, 1], BatchMode::Atomic).map(|r| r.len()), Ok(2));
+            assert_eq!(ssal.transfer(accounts.bob, 1_000, Vec::new()), Ok(()));
+            assert!(ssal.audit().is_sound());
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+            assert_eq!(ssal.audit(), AuditReport::default());
+        }
+
+        #[ink::test]
+        fn audit_fails() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.create_contracts(vec![rice_listing(1); 2]), Ok(vec![0, 1]));
+            assert_eq!(ssal.buy_contracts(vec![0, 1], BatchMode::Atomic).map(|r| r.len()), Ok(2));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+
+            // Tokens minted out of thin air
+            ssal.corrupt_balance(accounts.frank, 7);
+            assert_eq!(
+                ssal.audit().violations,
+                vec![Violation::SupplyMismatch { total_supply: 100_000, sum_of_balances: 100_007 }]
+            );
+            ssal.corrupt_balance(accounts.frank, 0);
+
+            // Pool drained below what unsettled contracts are owed
+            ssal.corrupt_balance(accounts.django, 9_000);
+            ssal.corrupt_balance(accounts.frank, 1_000);
+            assert_eq!(
+                ssal.audit().violations,
+                vec![Violation::EscrowMismatch { pooled: 9_000, outstanding: 10_000 }]
+            );
+            ssal.corrupt_balance(accounts.django, 10_000);
+            ssal.corrupt_balance(accounts.frank, 0);
+
+            // Settled contract left with escrow
+            ssal.corrupt_escrow(0, 10_000);
+            assert_eq!(
+                ssal.audit().violations,
+                vec![Violation::SettledEscrow { contract_id: 0, escrow: 10_000 }]
+            );
+            assert!(!ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn finalize_works() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice owns all the tokens on contract instantiation
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
+
+            // Create new contract and buy
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(0));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+
+            // Try finalizing after finality block 
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+        }
+
+        #[ink::test]
+        fn finalize_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+
+            // Alice owns all the tokens on contract instantiation
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
+
+            assert_eq!(ssal.finalize(0), Err(Error::ContractNotFound));
+
+            // Create new contract and buy
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(0));
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            
+            // Try finalizing at finality block 
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Err(Error::CannotFinalizeBeforeFinalityBlock));
+
+            // Try finalizing after finality block 
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.finalize(0), Ok(()));
+
+            // Try finalizing again on the same contract 
+            assert_eq!(ssal.finalize(0), Err(Error::ContractAlreadyFinalized));
+
+            // Try finalizing contract after finality block that has not been bought
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 3), Ok(1));
+            for _ in 1..5 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(ssal.finalize(1), Err(Error::ContractNotPurchased));
+            
+        }
+
+        fn rice_spec() -> QualitySpec {
+            QualitySpec { grade: 2, moisture: 1_400, broken_kernels: 500, variety: 7 }
+        }
+
+        fn rice_schedule() -> QualitySchedule {
+            QualitySchedule {
+                moisture_discount: 50,
+                broken_kernel_discount: 20,
+                grade_premium: 100,
+                grade_discount: 200,
+                variety_discount: 1_000,
+            }
         }
 
-        /// We test a simple use case of our contract.
         #[ink::test]
-        fn create_contract_works() {
-            let mut ssal = SsalCommods::new(100_000);
+        fn quality_schedule_discount_works() {
+            let spec = rice_spec();
+            let schedule = rice_schedule();
+
+            // Reading matches the spec exactly
+            assert_eq!(schedule.discount(&spec, &spec), 0);
+            // 2 points of moisture above spec at 0.5% per point
+            let wet = QualitySpec { moisture: 1_600, ..spec };
+            assert_eq!(schedule.discount(&spec, &wet), 100);
+            // Half a point of broken kernels above spec at 0.2% per point
+            let broken = QualitySpec { broken_kernels: 550, ..spec };
+            assert_eq!(schedule.discount(&spec, &broken), 10);
+            // One grade worse plus the wrong variety
+            let poor = QualitySpec { grade: 3, variety: 8, ..spec };
+            assert_eq!(schedule.discount(&spec, &poor), 1_200);
+            // Premium offsets the moisture discount but never turns negative
+            let better = QualitySpec { grade: 1, moisture: 1_600, ..spec };
+            assert_eq!(schedule.discount(&spec, &better), 0);
+            // Discount is capped at the full total
+            let spoiled = QualitySpec { moisture: 10_000, variety: 8, ..spec };
+            assert_eq!(schedule.discount(&spec, &spoiled), 5_300);
+            let strict = QualitySchedule { variety_discount: MAX_BASIS_POINTS, ..schedule };
+            assert_eq!(strict.discount(&spec, &spoiled), MAX_BASIS_POINTS);
+        }
 
+        #[ink::test]
+        fn finalize_with_quality_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // Hold the escrow on an account of its own
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
 
-            // Alice owns all the tokens on contract instantiation
-            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
-            // Bob does not owns tokens
-            assert_eq!(ssal.balance_of(accounts.bob), 0);
+            // Bob lists a graded lot which Alice buys
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(0));
+            assert_eq!(ssal.set_quality(0, rice_spec(), rice_schedule()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000 - 10 - 10_000);
 
-            // Test correct input.
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 20), Ok(()));
-            assert_eq!(ssal.get_contract_count(), 1);
-            assert_eq!(ssal.get_seller(0), Some(accounts.alice));
-            assert_eq!(ssal.get_buyer(0), None);
-            assert_eq!(ssal.get_finality_block(0), Some(20));
-            assert_eq!(ssal.get_price(0), Some(10));
-            assert_eq!(ssal.get_total(0), Some(10_000));
-            assert_eq!(ssal.get_weight(0), Some(10));
+            // Neither party can grade the lot itself
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let reading = QualitySpec { moisture: 1_600, ..rice_spec() };
+            assert_eq!(ssal.grant_role(Role::Oracle, accounts.alice), Ok(()));
+            assert_eq!(ssal.finalize_with_quality(0, reading), Err(Error::OnlyOracleCanGrade));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.finalize_with_quality(0, reading), Err(Error::OnlyOracleCanGrade));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.finalize_with_quality(0, reading), Err(Error::OnlyOracleCanGrade));
+
+            // Eve reports the lot 2 points too wet: 1% of total goes back to Alice
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.grant_role(Role::Oracle, accounts.eve), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.finalize_with_quality(0, reading), Ok(()));
+            assert_eq!(ssal.get_quality_reading(0), Some(reading));
+            assert_eq!(ssal.balance_of(accounts.bob), 10 + 9_900);
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000 - 10 - 9_900);
+            assert_eq!(ssal.balance_of(accounts.django), 0);
         }
 
-        // Test possible failiures for create_contract
         #[ink::test]
-        fn create_contract_fails() {
-            let mut ssal = SsalCommods::new(100_000);
-
+        fn protocol_fees_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.get_protocol_fees(), None);
 
-            // Alice owns all the tokens on contract instantiation
-            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
-            // Bob does not owns tokens
-            assert_eq!(ssal.balance_of(accounts.bob), 0);
+            // 5% of the price and 1% of the settled total go to Frank
+            assert_eq!(ssal.set_protocol_fees(500, 100, accounts.frank), Ok(()));
+            assert_eq!(
+                ssal.get_protocol_fees(),
+                Some(ProtocolFees { purchase_fee: 500, settlement_fee: 100, treasury: accounts.frank })
+            );
 
-            // Test correct input.
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 20), Ok(()));
-            assert_eq!(ssal.get_contract_count(), 1);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 1_000, 10_000, 10, 1), Ok(0));
+            assert_eq!(ssal.set_quality(0, rice_spec(), rice_schedule()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 950);
+            assert_eq!(ssal.balance_of(accounts.frank), 50);
+
+            // The fee is taken from what the seller is owed after the quality discount
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let reading = QualitySpec { moisture: 1_600, ..rice_spec() };
+            assert_eq!(ssal.grant_role(Role::Oracle, accounts.eve), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.finalize_with_quality(0, reading), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 950 + 9_801);
+            assert_eq!(ssal.balance_of(accounts.frank), 50 + 99);
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000 - 11_000 + 100);
+            assert_eq!(ssal.balance_of(accounts.django), 0);
+            assert!(ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn protocol_fees_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
 
-            // Test faulty input.
-            // Advance block number by 5
-            for _ in 1..5{
-                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            }
             assert_eq!(
-                ssal.create_contract(10, 10_000, 10, 1), 
-                Err(Error::InvalidBlockNumber)
+                ssal.set_protocol_fees(MAX_PROTOCOL_FEE + 1, 0, accounts.frank),
+                Err(Error::InvalidProtocolFee)
             );
-            assert_eq!(ssal.get_contract_count(), 1);
+            assert_eq!(
+                ssal.set_protocol_fees(0, MAX_PROTOCOL_FEE + 1, accounts.frank),
+                Err(Error::InvalidProtocolFee)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.set_protocol_fees(100, 100, accounts.bob), Err(Error::NotOwner));
+            assert_eq!(ssal.get_protocol_fees(), None);
         }
 
-        /// We test a simple use case of our contract.
         #[ink::test]
-        fn buy_contract_works() {
-            let mut ssal = SsalCommods::new(100_000);
+        fn quality_fails() {
+            let mut ssal = new_market(100_000);
 
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
 
-            // Alice owns all the tokens on contract instantiation
-            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
-            // Bob does not owns tokens
-            assert_eq!(ssal.balance_of(accounts.bob), 0);
+            assert_eq!(
+                ssal.set_quality(0, rice_spec(), rice_schedule()),
+                Err(Error::ContractNotFound)
+            );
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 10, 1), Ok(0));
+
+            // Rates above 100% are rejected
+            let schedule = QualitySchedule { variety_discount: 10_001, ..rice_schedule() };
+            assert_eq!(
+                ssal.set_quality(0, rice_spec(), schedule),
+                Err(Error::InvalidQualitySchedule)
+            );
 
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 20), Ok(()));
+            // Only the seller can set the spec
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                ssal.set_quality(0, rice_spec(), rice_schedule()),
+                Err(Error::OnlySellerCanModify)
+            );
 
-            // Transfer funds to bob so he can purchase the contract.
-            // Make sure bob has 0 cash by default.
-            assert_eq!(ssal.balance_of(accounts.bob), 0);
-            // Alice transfers 10 tokens to Bob.
-            assert_eq!(ssal.transfer(accounts.bob, 10_000), Ok(()));
-            // Bob owns 10 tokens.
-            assert_eq!(ssal.balance_of(accounts.bob), 10_000);
-            
-            // Try to buy in correct case 
+            // Contracts without a spec cannot be graded
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(ssal.buy_contract(0), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.grant_role(Role::Oracle, accounts.eve), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                ssal.finalize_with_quality(0, rice_spec()),
+                Err(Error::QualitySpecNotFound)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+
+            // Terms are frozen once the contract is bought
+            assert_eq!(
+                ssal.set_quality(0, rice_spec(), rice_schedule()),
+                Err(Error::ContractAlreadyBought)
+            );
         }
 
-        // Test possible failiures for buy_contract
         #[ink::test]
-        fn buy_contract_fails() {
-            let mut ssal = SsalCommods::new(100_000);
-
+        fn attest_delivery_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
 
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 20), Ok(()));
-
-            // Alice transfers 10 tokens to Bob.
-            assert_eq!(ssal.transfer(accounts.bob, 10_000), Ok(()));
-            
-            // Try to buy in correct case 
+            // Bob lists a graded lot inspected by Charlie, which Alice buys
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 1_000, 1), Ok(0));
+            assert_eq!(ssal.set_quality(0, rice_spec(), rice_schedule()), Ok(()));
+            assert_eq!(ssal.set_inspector(0, accounts.charlie, 100), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(ssal.buy_contract(0), Ok(()));
-            // Try to buy the contract that has just been bought
-            assert_eq!(ssal.buy_contract(0), Err(Error::ContractAlreadyBought));
-            // Try to buy a contract that does not exist
-            assert_eq!(ssal.buy_contract(1), Err(Error::ContractNotFound));
-            // Try to buy variations of contracts that cost too much
-            assert_eq!(ssal.create_contract(100_000, 100_000, 10, 20), Ok(()));
-            assert_eq!(ssal.create_contract(100_000, 0, 10, 20), Ok(()));
-            assert_eq!(ssal.create_contract(0, 100_000, 10, 20), Ok(()));
-            assert_eq!(ssal.buy_contract(1), Err(Error::InsufficientBalance));
-            assert_eq!(ssal.buy_contract(2), Err(Error::InsufficientBalance));
-            assert_eq!(ssal.buy_contract(3), Err(Error::InsufficientBalance));
+
+            // Charlie attests before the finality block, which does not settle yet
+            let reading = QualitySpec { moisture: 1_600, ..rice_spec() };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                ssal.attest_delivery(0, 500, Some(reading), Hash::from([7; 32])),
+                Ok(())
+            );
+            let attestation = ssal.get_attestation(0).unwrap();
+            assert_eq!(attestation.inspector, accounts.charlie);
+            assert_eq!(attestation.delivered_grams, 500);
+            assert_eq!(ssal.is_finalized(0), Some(false));
+
+            // Anyone can settle on the attestation after the finality block:
+            // half the lot was delivered 1% under spec, minus the inspector fee.
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.finalize(0), Ok(()));
+            assert_eq!(ssal.get_quality_reading(0), Some(reading));
+            assert_eq!(ssal.balance_of(accounts.bob), 10 + 4_950 - 100);
+            assert_eq!(ssal.balance_of(accounts.charlie), 100);
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000 - 10 - 4_950);
+            assert_eq!(ssal.balance_of(accounts.django), 0);
         }
 
         #[ink::test]
-        fn finalize_works() {
-            let mut ssal = SsalCommods::new(100_000);
+        fn attest_delivery_fails() {
+            let mut ssal = new_market(100_000);
 
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let report = Hash::from([7; 32]);
 
-            // Alice owns all the tokens on contract instantiation
-            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 1_000, 1), Ok(0));
+            assert_eq!(
+                ssal.set_inspector(0, accounts.charlie, 10_001),
+                Err(Error::InvalidInspectorFee)
+            );
 
-            // Create new contract and buy
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 1), Ok(()));
+            // Inspectors hold the Inspector role and are neither seller nor buyer
+            assert_eq!(
+                ssal.set_inspector(0, accounts.charlie, 100),
+                Err(Error::InvalidInspector)
+            );
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.alice), Ok(()));
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+            assert_eq!(
+                ssal.set_inspector(0, accounts.alice, 100),
+                Err(Error::InvalidInspector)
+            );
+            assert_eq!(ssal.set_inspector(0, accounts.charlie, 100), Ok(()));
+            assert_eq!(
+                ssal.attest_delivery(0, 1_000, None, report),
+                Err(Error::ContractNotPurchased)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(ssal.buy_contract(0), Err(Error::InvalidInspector));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(ssal.buy_contract(0), Ok(()));
 
-            // Try finalizing after finality block 
+            // Only the inspector can attest, and only once
+            assert_eq!(
+                ssal.attest_delivery(0, 1_000, None, report),
+                Err(Error::OnlyInspectorCanAttest)
+            );
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            assert_eq!(ssal.finalize(0), Ok(()));
+
+            // The buyer's word is not enough on an inspected contract
+            assert_eq!(ssal.finalize(0), Err(Error::AttestationNotFound));
+            assert_eq!(
+                ssal.finalize_with_quality(0, rice_spec()),
+                Err(Error::OnlyInspectorCanGrade)
+            );
+
+            // Inspectors who lost the role cannot attest
+            assert_eq!(ssal.revoke_role(Role::Inspector, accounts.charlie), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                ssal.attest_delivery(0, 1_000, None, report),
+                Err(Error::OnlyInspectorCanAttest)
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+
+            // Readings need a spec to be graded against
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                ssal.attest_delivery(0, 1_000, Some(rice_spec()), report),
+                Err(Error::QualitySpecNotFound)
+            );
+
+            // Attesting after the finality block settles right away
+            assert_eq!(ssal.attest_delivery(0, 1_000, None, report), Ok(()));
+            assert_eq!(ssal.is_finalized(0), Some(true));
+            assert_eq!(
+                ssal.attest_delivery(0, 1_000, None, report),
+                Err(Error::ContractAlreadyFinalized)
+            );
         }
 
         #[ink::test]
-        fn finalize_fails() {
-            let mut ssal = SsalCommods::new(100_000);
-
+        fn arbitrate_works() {
             let accounts =
                 ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.eve), Ok(()));
 
-            // Alice owns all the tokens on contract instantiation
-            assert_eq!(ssal.balance_of(accounts.alice), 100_000);
-
-            assert_eq!(ssal.finalize(0), Err(Error::ContractNotFound));
-
-            // Create new contract and buy
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 1), Ok(()));
+            // Bob lists a graded lot inspected by Charlie, which Alice buys
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 1_000, 1), Ok(0));
+            assert_eq!(ssal.set_quality(0, rice_spec(), rice_schedule()), Ok(()));
+            assert_eq!(ssal.set_inspector(0, accounts.charlie, 100), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
             assert_eq!(ssal.buy_contract(0), Ok(()));
-            
-            // Try finalizing at finality block 
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            assert_eq!(ssal.finalize(0), Err(Error::CannotFinalizeBeforeFinalityBlock));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
 
-            // Try finalizing after finality block 
+            // Charlie does not attest and Eve rules half the lot was delivered 1%
+            // under spec, so Charlie earns no fee
+            let reading = QualitySpec { moisture: 1_600, ..rice_spec() };
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.arbitrate(0, 500, Some(reading)), Ok(()));
+            assert_eq!(ssal.is_finalized(0), Some(true));
+            assert_eq!(ssal.get_quality_reading(0), Some(reading));
+            assert_eq!(ssal.balance_of(accounts.bob), 10 + 4_950);
+            assert_eq!(ssal.balance_of(accounts.charlie), 0);
+            assert_eq!(ssal.balance_of(accounts.alice), 100_000 - 10 - 4_950);
+            assert_eq!(ssal.balance_of(accounts.django), 0);
+            assert!(ssal.audit().is_sound());
+        }
+
+        #[ink::test]
+        fn arbitrate_fails() {
+            let mut ssal = new_market(100_000);
+
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.alice), Ok(()));
+            assert_eq!(ssal.grant_role(Role::Arbiter, accounts.eve), Ok(()));
+            assert_eq!(ssal.arbitrate(0, 1_000, None), Err(Error::ContractNotFound));
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 1_000, 1), Ok(0));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                ssal.arbitrate(0, 1_000, None),
+                Err(Error::CannotFinalizeBeforeFinalityBlock)
+            );
             ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
-            assert_eq!(ssal.finalize(0), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(ssal.arbitrate(0, 1_000, None), Err(Error::ContractNotPurchased));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.transfer(accounts.bob, 20_000, Vec::new()), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
 
-            // Try finalizing again on the same contract 
-            assert_eq!(ssal.finalize(0), Err(Error::ContractAlreadyFinalized));
+            // Arbiters are neutral: not the seller, the buyer or an account without the role
+            for party in [accounts.alice, accounts.bob, accounts.frank] {
+                ink::env::test::set_caller::<ink::env::DefaultEnvironment>(party);
+                assert_eq!(ssal.arbitrate(0, 1_000, None), Err(Error::OnlyArbiterCanResolve));
+            }
 
-            // Try finalizing contract after finality block that has not been bought
-            assert_eq!(ssal.create_contract(10, 10_000, 10, 3), Ok(()));
-            for _ in 1..5 {
+            // Readings need a spec to be graded against
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(
+                ssal.arbitrate(0, 1_000, Some(rice_spec())),
+                Err(Error::QualitySpecNotFound)
+            );
+
+            // Rulings are settlements, halted by a pause
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.pause(PauseScope::Settlement), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.arbitrate(0, 1_000, None), Err(Error::Paused));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.unpause(PauseScope::Settlement), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.arbitrate(0, 0, None), Ok(()));
+            assert_eq!(ssal.arbitrate(0, 1_000, None), Err(Error::ContractAlreadyFinalized));
+
+            // Nothing delivered pays the seller nothing, without a zero-value transfer
+            assert_eq!(ssal.balance_of(accounts.bob), 20_000 - 10);
+            type Event = <SsalCommods as ink::reflect::ContractEventBase>::Type;
+            let zero_transfers = ink::env::test::recorded_events()
+                .filter_map(|event| <Event as scale::Decode>::decode(&mut &event.data[..]).ok())
+                .filter(|event| matches!(event, Event::Transfer(Transfer { value: 0, .. })))
+                .count();
+            assert_eq!(zero_transfers, 0);
+        }
+
+        #[ink::test]
+        fn attestation_deadline_works() {
+            let accounts =
+                ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.django);
+            let mut ssal = new_market(100_000);
+            assert_eq!(ssal.grant_role(Role::Inspector, accounts.charlie), Ok(()));
+
+            // Bob lists a lot inspected by Charlie, which Alice buys
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 1_000, 1), Ok(0));
+            assert_eq!(ssal.set_inspector(0, accounts.charlie, 100), Ok(()));
+            assert_eq!(ssal.get_attestation_deadline(0), Some(1 + ATTESTATION_WINDOW));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(ssal.buy_contract(0), Ok(()));
+
+            // Charlie never attests, which holds the escrow until the deadline
+            for _ in 0..=ATTESTATION_WINDOW {
                 ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
             }
-            assert_eq!(ssal.finalize(1), Err(Error::ContractNotPurchased));
-            
+            assert_eq!(ssal.finalize(0), Err(Error::AttestationNotFound));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            // Past it, only the buyer's word settles the contract, without a fee
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.eve);
+            assert_eq!(ssal.finalize(0), Err(Error::AttestationNotFound));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                ssal.finalize_with_quality(0, rice_spec()),
+                Err(Error::OnlyInspectorCanGrade)
+            );
+            assert_eq!(ssal.finalize(0), Ok(()));
+            assert_eq!(ssal.balance_of(accounts.bob), 10 + 10_000);
+            assert_eq!(ssal.balance_of(accounts.charlie), 0);
+            assert_eq!(ssal.balance_of(accounts.django), 0);
+
+            // Uninspected contracts have no deadline
+            assert_eq!(ssal.create_contract(RICE, 10, 10_000, 1_000, 20_000), Ok(1));
+            assert_eq!(ssal.get_attestation_deadline(1), None);
         }
 
     }
@@ -774,7 +7392,7 @@ mod ssal_commods {
             let bob_account = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
             let transfer_to_bob = 500_000_000u128;
             let transfer = build_message::<SsalCommodsRef>(contract_acc_id.clone())
-                .call(|ssal_commods| ssal_commods.transfer(bob_account.clone(), transfer_to_bob));
+                .call(|ssal_commods| ssal_commods.transfer(bob_account.clone(), transfer_to_bob, Vec::new()));
             let _transfer_res = client
                 .call(&ink_e2e::alice(), transfer, 0, None)
                 .await
@@ -786,8 +7404,20 @@ mod ssal_commods {
                 .call_dry_run(&ink_e2e::alice(), &balance_of, 0, None)
                 .await;
 
+            let register_series = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.register_series(
+                    String::from("Japonica"),
+                    1,
+                    String::from("Busan"),
+                    Unit::Tonnes,
+                ));
+            let _register_series_res = client
+                .call(&ink_e2e::alice(), register_series, 0, None)
+                .await
+                .expect("register series failed");
+
             let create_contract = build_message::<SsalCommodsRef>(contract_acc_id.clone())
-                .call(|ssal_commods| ssal_commods.create_contract(10_000, 1_000_000, 100, 3));
+                .call(|ssal_commods| ssal_commods.create_contract(0, 10_000, 1_000_000, 100, 3));
             let _create_contract_res = client
                 .call(&ink_e2e::alice(), create_contract, 0, None)
                 .await
@@ -889,14 +7519,26 @@ mod ssal_commods {
             let bob_account = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
             let transfer_to_bob = 500_000_000u128;
             let transfer = build_message::<SsalCommodsRef>(contract_acc_id.clone())
-                .call(|ssal_commods| ssal_commods.transfer(bob_account.clone(), transfer_to_bob));
+                .call(|ssal_commods| ssal_commods.transfer(bob_account.clone(), transfer_to_bob, Vec::new()));
             let _transfer_res = client
                 .call(&ink_e2e::alice(), transfer, 0, None)
                 .await
                 .expect("transfer failed");
 
+            let register_series = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.register_series(
+                    String::from("Japonica"),
+                    1,
+                    String::from("Busan"),
+                    Unit::Tonnes,
+                ));
+            let _register_series_res = client
+                .call(&ink_e2e::alice(), register_series, 0, None)
+                .await
+                .expect("register series failed");
+
             let create_contract = build_message::<SsalCommodsRef>(contract_acc_id.clone())
-                .call(|ssal_commods| ssal_commods.create_contract(10_000, 1_000_000, 100, 3));
+                .call(|ssal_commods| ssal_commods.create_contract(0, 10_000, 1_000_000, 100, 3));
             let _create_contract_res = client
                 .call(&ink_e2e::alice(), create_contract, 0, None)
                 .await
@@ -927,5 +7569,140 @@ mod ssal_commods {
 
             Ok(())
         }
+
+
+        #[ink_e2e::test]
+        async fn buy_and_finalize_in_psp22(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            // given
+            let total_supply = 1_000_000_000;
+            let constructor = SsalCommodsRef::new(total_supply);
+            let contract_acc_id = client
+                .instantiate("ssal_commods", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            // A second deployment of this contract stands in for an external stablecoin
+            let token_constructor = SsalCommodsRef::new_with_metadata(
+                total_supply,
+                Some(String::from("Stablecoin")),
+                Some(String::from("USDX")),
+                6,
+            );
+            let token_acc_id = client
+                .instantiate("ssal_commods", &ink_e2e::alice(), token_constructor, 0, None)
+                .await
+                .expect("instantiate token failed")
+                .account_id;
+
+            let alice_account = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob_account = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let transfer_to_bob = 500_000_000u128;
+            let transfer = build_message::<SsalCommodsRef>(token_acc_id.clone())
+                .call(|token| token.transfer(bob_account.clone(), transfer_to_bob, Vec::new()));
+            let _transfer_res = client
+                .call(&ink_e2e::alice(), transfer, 0, None)
+                .await
+                .expect("token transfer failed");
+
+            let register_series = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.register_series(
+                    String::from("Japonica"),
+                    1,
+                    String::from("Busan"),
+                    Unit::Tonnes,
+                ));
+            let _register_series_res = client
+                .call(&ink_e2e::alice(), register_series, 0, None)
+                .await
+                .expect("register series failed");
+
+            let create_contract = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.create_contract(0, 10_000, 1_000_000, 100, 6));
+            let _create_contract_res = client
+                .call(&ink_e2e::alice(), create_contract, 0, None)
+                .await
+                .expect("create contract failed");
+
+            let set_settlement_asset = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| {
+                    ssal_commods.set_settlement_asset(0, SettlementAsset::Psp22(token_acc_id.clone()))
+                });
+            let _set_settlement_asset_res = client
+                .call(&ink_e2e::alice(), set_settlement_asset, 0, None)
+                .await
+                .expect("set settlement asset failed");
+
+            // when
+            let buy_without_allowance = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.buy_contract(0));
+            let buy_without_allowance = client
+                .call(&ink_e2e::bob(), buy_without_allowance, 0, None)
+                .await;
+            assert!(
+                buy_without_allowance.is_err(),
+                "buying should fail until the buyer approves the contract on the token"
+            );
+
+            let approve = build_message::<SsalCommodsRef>(token_acc_id.clone())
+                .call(|token| token.approve(contract_acc_id.clone(), 1_010_000));
+            let _approve_res = client
+                .call(&ink_e2e::bob(), approve, 0, None)
+                .await
+                .expect("approve failed");
+            let buy_contract = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.buy_contract(0));
+            let _buy_contract_res = client
+                .call(&ink_e2e::bob(), buy_contract, 0, None)
+                .await
+                .expect("buy contract failed");
+
+            let token_balance_of = |account: AccountId| {
+                build_message::<SsalCommodsRef>(token_acc_id.clone())
+                    .call(|token| token.balance_of(account))
+            };
+            let pooled_after_buy = client
+                .call_dry_run(&ink_e2e::bob(), &token_balance_of(contract_acc_id.clone()), 0, None)
+                .await;
+            let seller_after_buy = client
+                .call_dry_run(&ink_e2e::bob(), &token_balance_of(alice_account.clone()), 0, None)
+                .await;
+            let escrow_of = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.escrow_of(0));
+            let escrow_of_res = client.call_dry_run(&ink_e2e::bob(), &escrow_of, 0, None).await;
+            let total_escrow = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.total_escrow());
+            let total_escrow_res = client.call_dry_run(&ink_e2e::bob(), &total_escrow, 0, None).await;
+
+            assert_eq!(pooled_after_buy.return_value(), 1_000_000);
+            assert_eq!(seller_after_buy.return_value(), 500_010_000);
+            assert_eq!(escrow_of_res.return_value(), 1_000_000);
+            assert_eq!(total_escrow_res.return_value(), 0);
+
+            let finalize = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.finalize(0));
+            let _finalize_res = client
+                .call(&ink_e2e::bob(), finalize, 0, None)
+                .await
+                .expect("finalize contract failed");
+
+            // then
+            let pooled_after_finalize = client
+                .call_dry_run(&ink_e2e::bob(), &token_balance_of(contract_acc_id.clone()), 0, None)
+                .await;
+            let seller_after_finalize = client
+                .call_dry_run(&ink_e2e::bob(), &token_balance_of(alice_account.clone()), 0, None)
+                .await;
+            let internal_balance_of = build_message::<SsalCommodsRef>(contract_acc_id.clone())
+                .call(|ssal_commods| ssal_commods.balance_of(bob_account));
+            let internal_balance_of_res = client
+                .call_dry_run(&ink_e2e::bob(), &internal_balance_of, 0, None)
+                .await;
+
+            assert_eq!(pooled_after_finalize.return_value(), 0);
+            assert_eq!(seller_after_finalize.return_value(), 501_010_000);
+            assert_eq!(internal_balance_of_res.return_value(), 0);
+
+            Ok(())
+        }
     }
 }